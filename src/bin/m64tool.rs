@@ -0,0 +1,152 @@
+/*
+ * src/bin/m64tool.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Inspect and edit the frames of movies.
+
+use std::{env, io, ops::Range, process};
+use tasinput2::{Button, ClampMode, Interpolation, Keyframe, Movie, Ramp, CONTROLLER_COUNT};
+
+const USAGE: &str = "Usage:
+    m64tool info <movie.m64>
+    m64tool insert <movie.m64> <frame> <count>
+    m64tool delete <movie.m64> <start> <end>
+    m64tool button <movie.m64> <port> <button> <start> <end> <0|1>
+    m64tool stick <movie.m64> <port> <start> <end> <x> <y>
+    m64tool ramp <movie.m64> <port> <start> <end> <x> <y> <to x> <to y> <linear|eased|arc>
+    m64tool copy <movie.m64> <port> <start> <end> <to port> <to frame>
+    m64tool shift <movie.m64> <port> <frame> <offset>
+
+Ports count from 1, and ranges of frames include the start but not the end.";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+// parse a number from the command line
+fn number<T: std::str::FromStr>(arg: &str) -> T {
+    arg.parse().unwrap_or_else(|_| {
+        eprintln!("{} is not a valid number", arg);
+        process::exit(2);
+    })
+}
+
+fn port(arg: &str) -> usize {
+    match number::<usize>(arg) {
+        p if (1..=CONTROLLER_COUNT).contains(&p) => p - 1,
+        _ => {
+            eprintln!("{} is not a controller port", arg);
+            process::exit(2);
+        }
+    }
+}
+
+fn range(start: &str, end: &str) -> Range<usize> {
+    match number(start)..number(end) {
+        r if r.start <= r.end => r,
+        _ => {
+            eprintln!("{} comes after {}", start, end);
+            process::exit(2);
+        }
+    }
+}
+
+fn run(args: &[String]) -> io::Result<()> {
+    let (command, path) = match (args.first(), args.get(1)) {
+        (Some(c), Some(p)) => (c.as_str(), p),
+        _ => usage(),
+    };
+    let mut movie = Movie::load(path)?;
+
+    match (command, &args[2..]) {
+        ("info", []) => {
+            let header = &movie.header;
+            println!("ROM: {} ({:08X})", header.rom_name, header.rom_crc);
+            println!("Author: {}", header.author);
+            println!("Frames: {}", movie.len());
+            println!("Rerecords: {}", header.rerecords);
+            for (i, p) in header.ports.iter().enumerate().filter(|(_, p)| p.present) {
+                println!("Port {}: {}", i + 1, p.pak.name());
+            }
+            for event in movie.events.events() {
+                println!("{}", event);
+            }
+            for marker in movie.markers.list() {
+                println!(
+                    "Marker {}: {} {}",
+                    marker.frame, marker.name, marker.comment
+                );
+            }
+            return Ok(());
+        }
+        ("insert", [frame, count]) => movie.insert_empty(number(frame), number(count)),
+        ("delete", [start, end]) => {
+            movie.delete_frames(range(start, end));
+        }
+        ("button", [p, button, start, end, pressed]) => {
+            let button = Button::from_name(button).unwrap_or_else(|| {
+                eprintln!("{} is not a button", button);
+                process::exit(2);
+            });
+            let pressed = number::<u8>(pressed) != 0;
+            movie.set_button(port(p), button, range(start, end), pressed);
+        }
+        ("stick", [p, start, end, x, y]) => {
+            movie.set_stick(port(p), range(start, end), number(x), number(y))
+        }
+        ("ramp", [p, start, end, x, y, to_x, to_y, interpolation]) => {
+            let interpolation = match interpolation.as_str() {
+                "linear" => Interpolation::Linear,
+                "eased" => Interpolation::Eased,
+                "arc" => Interpolation::Angular,
+                _ => {
+                    eprintln!("{} is not an interpolation", interpolation);
+                    process::exit(2);
+                }
+            };
+            let range = range(start, end);
+            if !range.is_empty() {
+                let ramp = Ramp::new(
+                    Keyframe::new(range.start as u64, number(x), number(y)),
+                    Keyframe::new(range.end as u64 - 1, number(to_x), number(to_y)),
+                    interpolation,
+                    ClampMode::Raw,
+                );
+                movie.set_ramp(port(p), &ramp);
+            }
+        }
+        ("copy", [p, start, end, to_port, to_frame]) => {
+            let inputs = movie.copy(port(p), range(start, end));
+            movie.paste(port(to_port), number(to_frame), &inputs);
+        }
+        ("shift", [p, frame, offset]) => movie.shift(port(p), number(frame), number(offset)),
+        _ => usage(),
+    }
+
+    movie.save(path)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,111 @@
+/*
+ * src/bin/mpktool.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Inspect and edit Controller Pak images.
+
+use std::{env, fs, process};
+use tasinput2::{Mpk, MpkError};
+
+const USAGE: &str = "Usage:
+    mpktool list <pak.mpk>
+    mpktool check <pak.mpk>
+    mpktool format <pak.mpk>
+    mpktool export <pak.mpk> <note> <file.note>
+    mpktool import <pak.mpk> <file.note>
+    mpktool delete <pak.mpk> <note>";
+
+// parse the index of a note from the command line
+fn note_index(arg: &str) -> usize {
+    arg.parse().unwrap_or_else(|_| {
+        eprintln!("{} is not a note index", arg);
+        process::exit(2);
+    })
+}
+
+fn run(args: &[String]) -> Result<(), MpkError> {
+    let (command, path) = match (args.get(0), args.get(1)) {
+        (Some(c), Some(p)) => (c.as_str(), p),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    match (command, &args[2..]) {
+        ("list", []) => {
+            let mpk = Mpk::load(path)?;
+            for note in mpk.notes()? {
+                let name = match note.extension.as_str() {
+                    "" => note.name.clone(),
+                    ext => format!("{}.{}", note.name, ext),
+                };
+                println!(
+                    "{:2}  {}  {:3} pages  {}",
+                    note.index,
+                    note.game_id(),
+                    note.pages.len(),
+                    name
+                );
+            }
+            println!("{} pages free", mpk.free_pages());
+        }
+        ("check", []) => {
+            let problems = Mpk::load(path)?.validate();
+            for problem in problems.iter() {
+                println!("{}", problem);
+            }
+            if problems.is_empty() {
+                println!("No problems found");
+            } else {
+                process::exit(1);
+            }
+        }
+        ("format", []) => Mpk::formatted().save(path)?,
+        ("export", [note, out]) => {
+            let mpk = Mpk::load(path)?;
+            fs::write(out, mpk.export_note(note_index(note))?)?;
+        }
+        ("import", [file]) => {
+            let mut mpk = Mpk::load(path)?;
+            let index = mpk.import_note(&fs::read(file)?)?;
+            mpk.save(path)?;
+            println!("Imported as note {}", index);
+        }
+        ("delete", [note]) => {
+            let mut mpk = Mpk::load(path)?;
+            mpk.delete_note(note_index(note))?;
+            mpk.save(path)?;
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
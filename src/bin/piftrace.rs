@@ -0,0 +1,48 @@
+/*
+ * src/bin/piftrace.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Render a trace of controller commands human-readably.
+
+use std::{
+    env, fs,
+    io::{self, Read},
+    process,
+};
+use tasinput2::TraceEntry;
+
+fn main() {
+    // read the trace from a file, or from standard input if none is given
+    let mut contents = String::new();
+    let result = match env::args().nth(1) {
+        Some(path) => fs::read_to_string(path).map(|c| contents = c),
+        None => io::stdin().read_to_string(&mut contents).map(|_| ()),
+    };
+    if let Err(e) = result {
+        eprintln!("Unable to read trace: {}", e);
+        process::exit(1);
+    }
+
+    for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+        match TraceEntry::parse(line) {
+            Some(entry) => println!("{}", entry),
+            None => println!("unreadable line: {}", line),
+        }
+    }
+}
@@ -0,0 +1,271 @@
+/*
+ * src/branch_panel.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::{
+    core_lib::Core,
+    movie::{first_difference, Branch},
+    state::SharedState,
+};
+use qt_widgets::{
+    cpp_core::{CppBox, MutPtr},
+    qt_core::{QString, Slot},
+    QGridLayout, QLabel, QLineEdit, QListWidget, QPushButton, QVBoxLayout, QWidget,
+};
+use std::rc::Rc;
+
+// the line for a branch in the list
+fn branch_text(branch: &Branch) -> String {
+    if branch.description().is_empty() {
+        format!("{} (frame {})", branch.name, branch.frame())
+    } else {
+        format!(
+            "{} (frame {}): {}",
+            branch.name,
+            branch.frame(),
+            branch.description()
+        )
+    }
+}
+
+/// A list of the branches of the movie, each with a savestate to return to.
+#[allow(dead_code)]
+pub struct BranchPanel<'a> {
+    pub container: CppBox<QWidget>,
+
+    list: MutPtr<QListWidget>,
+    name_edit: MutPtr<QLineEdit>,
+    description_edit: MutPtr<QLineEdit>,
+    status: MutPtr<QLabel>,
+    save_button: MutPtr<QPushButton>,
+    load_button: MutPtr<QPushButton>,
+    compare_button: MutPtr<QPushButton>,
+    promote_button: MutPtr<QPushButton>,
+    delete_button: MutPtr<QPushButton>,
+
+    save_clicked: Slot<'a>,
+    load_clicked: Slot<'a>,
+    compare_clicked: Slot<'a>,
+    promote_clicked: Slot<'a>,
+    delete_clicked: Slot<'a>,
+}
+
+impl<'a> BranchPanel<'a> {
+    /// Instantiate a new branch panel. Savestates are only made and loaded with a `core`.
+    pub fn new(shared: &'a SharedState, core: Option<&'a Core>) -> BranchPanel<'a> {
+        let mut container = unsafe { QWidget::new_0a() };
+        let mut layout = unsafe { QVBoxLayout::new_1a(&mut container).into_ptr() };
+
+        let (list, name_edit, description_edit, status) = unsafe {
+            let mut list = QListWidget::new_0a();
+            layout.add_widget(&mut list);
+
+            let mut name_edit = QLineEdit::new_0a();
+            name_edit.set_placeholder_text(&QString::from_std_str("Name"));
+            layout.add_widget(&mut name_edit);
+            let mut description_edit = QLineEdit::new_0a();
+            description_edit.set_placeholder_text(&QString::from_std_str("Description"));
+            layout.add_widget(&mut description_edit);
+
+            let mut status = QLabel::new_0a();
+            status.set_word_wrap(true);
+            layout.add_widget(&mut status);
+            (
+                list.into_ptr(),
+                name_edit.into_ptr(),
+                description_edit.into_ptr(),
+                status.into_ptr(),
+            )
+        };
+
+        let mut buttons = unsafe {
+            let mut buttons = QWidget::new_0a();
+            let grid = QGridLayout::new_1a(&mut buttons).into_ptr();
+            layout.add_widget(&mut buttons);
+            buttons.into_ptr();
+            grid
+        };
+        let mut button = move |text: &str, row: i32, column: i32| unsafe {
+            let mut button = QPushButton::from_q_string(&QString::from_std_str(text));
+            buttons.add_widget_3a(&mut button, row, column);
+            button.into_ptr()
+        };
+        let save_button = button("Save Branch", 0, 0);
+        let load_button = button("Load", 0, 1);
+        let compare_button = button("Compare", 1, 0);
+        let promote_button = button("Promote", 1, 1);
+        let delete_button = button("Delete", 2, 0);
+
+        let show_status = move |text: String| unsafe {
+            let mut status = status;
+            status.set_text(&QString::from_std_str(text));
+        };
+
+        let fill = Rc::new(move || unsafe {
+            let mut list = list;
+            let row = list.current_row();
+            list.clear();
+            for branch in shared.branches.lock().unwrap().list() {
+                list.add_item_q_string(&QString::from_std_str(branch_text(branch)));
+            }
+            list.set_current_row_1a(row.min(list.count() - 1));
+        });
+        fill();
+
+        // the branch selected in the list
+        let selected = move || unsafe {
+            let row = list.current_row();
+            let branches = shared.branches.lock().unwrap();
+            if row < 0 {
+                None
+            } else {
+                branches.list().get(row as usize).cloned()
+            }
+        };
+
+        // the movie up to the frame being sent to the emulator, and a savestate of that frame
+        let save_clicked = unsafe {
+            let fill = fill.clone();
+            Slot::new(move || {
+                let name = name_edit.text().to_std_string();
+                let description = description_edit.text().to_std_string();
+                let frame = *shared.frame.lock().unwrap() as usize;
+
+                let mut branches = shared.branches.lock().unwrap();
+                let movie = shared.movie.lock().unwrap();
+                if let Err(e) = branches.add(&name, &description, &movie, frame) {
+                    show_status(format!("Unable to save branch: {}", e));
+                    return;
+                }
+                drop(movie);
+
+                let saved = match (core, branches.savestate_path(&name)) {
+                    (Some(core), Some(path)) => core.save_state(&path).map_err(|e| e.to_string()),
+                    _ => Err("there is no emulator to save the state of".to_string()),
+                };
+                drop(branches);
+                match saved {
+                    Ok(()) => show_status(format!("Saved branch {} on frame {}", name, frame)),
+                    Err(e) => show_status(format!("Unable to save the state of {}: {}", name, e)),
+                }
+                fill();
+            })
+        };
+
+        // the savestate is loaded along with the frames, and the movie goes on from there
+        let load_clicked = unsafe {
+            Slot::new(move || {
+                let branch = match selected() {
+                    Some(branch) => branch,
+                    None => return,
+                };
+                let path = shared.branches.lock().unwrap().savestate_path(&branch.name);
+                let loaded = match (core, path) {
+                    (Some(core), Some(path)) => core.load_state(&path).map_err(|e| e.to_string()),
+                    _ => Err("there is no emulator to load the state into".to_string()),
+                };
+                if let Err(e) = loaded {
+                    show_status(format!("Unable to load branch {}: {}", branch.name, e));
+                    return;
+                }
+
+                shared.edit_movie(None, |movie| {
+                    branch.restore(movie);
+                    movie.header.rerecords += 1;
+                });
+                *shared.frame.lock().unwrap() = branch.frame() as u64;
+                show_status(format!("Loaded branch {}", branch.name));
+            })
+        };
+
+        let compare_clicked = unsafe {
+            Slot::new(move || {
+                let branch = match selected() {
+                    Some(branch) => branch,
+                    None => return,
+                };
+                let movie = shared.movie.lock().unwrap();
+                let difference = first_difference(branch.movie.frames(), movie.frames());
+                let sizes = format!(
+                    "{} has {} frames and the movie has {}",
+                    branch.name,
+                    branch.frame(),
+                    movie.len()
+                );
+                show_status(match difference {
+                    Some(frame) => format!("{}; they differ from frame {}", sizes, frame),
+                    None => format!("{}; they have the same inputs", sizes),
+                });
+            })
+        };
+
+        // the frames of the branch become the movie, without going back to its savestate
+        let promote_clicked = unsafe {
+            Slot::new(move || {
+                if let Some(branch) = selected() {
+                    shared.edit_movie(None, |movie| branch.restore(movie));
+                    show_status(format!("Promoted branch {} to the movie", branch.name));
+                }
+            })
+        };
+
+        let delete_clicked = unsafe {
+            Slot::new(move || {
+                let branch = match selected() {
+                    Some(branch) => branch,
+                    None => return,
+                };
+                if let Err(e) = shared.branches.lock().unwrap().remove(&branch.name) {
+                    show_status(format!("Unable to delete branch {}: {}", branch.name, e));
+                }
+                fill();
+            })
+        };
+
+        let panel = BranchPanel {
+            container,
+
+            list,
+            name_edit,
+            description_edit,
+            status,
+            save_button,
+            load_button,
+            compare_button,
+            promote_button,
+            delete_button,
+
+            save_clicked,
+            load_clicked,
+            compare_clicked,
+            promote_clicked,
+            delete_clicked,
+        };
+
+        unsafe {
+            save_button.clicked().connect(&panel.save_clicked);
+            load_button.clicked().connect(&panel.load_clicked);
+            compare_button.clicked().connect(&panel.compare_clicked);
+            promote_button.clicked().connect(&panel.promote_clicked);
+            delete_button.clicked().connect(&panel.delete_clicked);
+        }
+
+        panel
+    }
+}
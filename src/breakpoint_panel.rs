@@ -0,0 +1,130 @@
+/*
+ * src/breakpoint_panel.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::{breakpoints::Breakpoint, state::SharedState};
+use qt_widgets::{
+    cpp_core::{CppBox, MutPtr},
+    qt_core::{QString, Slot},
+    QHBoxLayout, QLineEdit, QListWidget, QPushButton, QVBoxLayout, QWidget,
+};
+use std::rc::Rc;
+
+/// A list of the breakpoints that pause the emulator, with a field to add more.
+#[allow(dead_code)]
+pub struct BreakpointPanel<'a> {
+    pub container: CppBox<QWidget>,
+
+    list: MutPtr<QListWidget>,
+    edit: MutPtr<QLineEdit>,
+    add_button: MutPtr<QPushButton>,
+    remove_button: MutPtr<QPushButton>,
+
+    add_clicked: Slot<'a>,
+    remove_clicked: Slot<'a>,
+}
+
+impl<'a> BreakpointPanel<'a> {
+    /// Instantiate a new breakpoint panel.
+    pub fn new(shared: &'a SharedState) -> BreakpointPanel<'a> {
+        let mut container = unsafe { QWidget::new_0a() };
+        let mut layout = unsafe { QVBoxLayout::new_1a(&mut container).into_ptr() };
+
+        let (list, edit) = unsafe {
+            let mut list = QListWidget::new_0a();
+            layout.add_widget(&mut list);
+
+            // the same forms `Breakpoint::parse` reads
+            let mut edit = QLineEdit::new_0a();
+            edit.set_placeholder_text(&QString::from_std_str(
+                "Frame, \"end\", \"marker\" or port and button",
+            ));
+            layout.add_widget(&mut edit);
+            (list.into_ptr(), edit.into_ptr())
+        };
+
+        let mut buttons = unsafe {
+            let mut buttons = QWidget::new_0a();
+            let row = QHBoxLayout::new_1a(&mut buttons).into_ptr();
+            layout.add_widget(&mut buttons);
+            buttons.into_ptr();
+            row
+        };
+        let mut button = move |text: &str| unsafe {
+            let mut button = QPushButton::from_q_string(&QString::from_std_str(text));
+            buttons.add_widget(&mut button);
+            button.into_ptr()
+        };
+        let add_button = button("Add");
+        let remove_button = button("Remove");
+
+        let fill = Rc::new(move || unsafe {
+            let mut list = list;
+            list.clear();
+            for breakpoint in shared.breakpoints.lock().unwrap().list() {
+                list.add_item_q_string(&QString::from_std_str(breakpoint.to_string()));
+            }
+        });
+
+        let add_clicked = unsafe {
+            let fill = fill.clone();
+            Slot::new(move || {
+                let mut edit = edit;
+                let text = edit.text().to_std_string();
+                match Breakpoint::parse(&text) {
+                    Some(breakpoint) => {
+                        shared.breakpoints.lock().unwrap().add(breakpoint);
+                        edit.clear();
+                        fill();
+                    }
+                    None => dprintln!("Unable to read breakpoint \"{}\"", text),
+                }
+            })
+        };
+        let remove_clicked = unsafe {
+            Slot::new(move || {
+                let row = list.current_row();
+                if row >= 0 {
+                    shared.breakpoints.lock().unwrap().remove(row as usize);
+                    fill();
+                }
+            })
+        };
+
+        let panel = BreakpointPanel {
+            container,
+
+            list,
+            edit,
+            add_button,
+            remove_button,
+
+            add_clicked,
+            remove_clicked,
+        };
+
+        unsafe {
+            add_button.clicked().connect(&panel.add_clicked);
+            edit.return_pressed().connect(&panel.add_clicked);
+            remove_button.clicked().connect(&panel.remove_clicked);
+        }
+
+        panel
+    }
+}
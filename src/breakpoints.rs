@@ -0,0 +1,176 @@
+/*
+ * src/breakpoints.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::{movie::Movie, Button, CONTROLLER_COUNT};
+use std::fmt;
+
+/// A reason to pause the emulator before the inputs of a frame are sent.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Before a specific frame.
+    Frame(u64),
+    /// When a movie being played back runs out of frames.
+    MovieEnd,
+    /// When the movie starts pressing a button on a port, on the frame it is first pressed.
+    Press { port: usize, button: Button },
+    /// Before any frame with a marker on it.
+    Marker,
+}
+
+impl Breakpoint {
+    /// Read a breakpoint written as a frame number, `end`, `marker`, or a port and a button name
+    /// such as `1 S`.
+    pub fn parse(text: &str) -> Option<Breakpoint> {
+        let mut parts = text.split_whitespace();
+        let first = parts.next()?;
+        let breakpoint = match parts.next() {
+            None if first.eq_ignore_ascii_case("end") => Breakpoint::MovieEnd,
+            None if first.eq_ignore_ascii_case("marker") => Breakpoint::Marker,
+            None => Breakpoint::Frame(first.parse().ok()?),
+            Some(button) => {
+                let port = first.parse::<usize>().ok()?.checked_sub(1)?;
+                if port >= CONTROLLER_COUNT {
+                    return None;
+                }
+                Breakpoint::Press {
+                    port,
+                    button: Button::from_name(button)?,
+                }
+            }
+        };
+        match parts.next() {
+            Some(_) => None,
+            None => Some(breakpoint),
+        }
+    }
+
+    /// Tell whether the emulator should pause before `frame` of a movie.
+    pub fn hits(self, frame: u64, movie: &Movie, playing: bool) -> bool {
+        match self {
+            Breakpoint::Frame(at) => frame == at,
+            Breakpoint::MovieEnd => playing && !movie.is_empty() && frame == movie.len() as u64,
+            Breakpoint::Press { port, button } => {
+                let pressed = |frame: u64| match movie.frame(frame as usize) {
+                    Some(inputs) => inputs[port].button(button),
+                    None => false,
+                };
+                pressed(frame) && (frame == 0 || !pressed(frame - 1))
+            }
+            Breakpoint::Marker => movie.markers.at(frame).is_some(),
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Frame(frame) => write!(f, "Frame {}", frame),
+            Breakpoint::MovieEnd => write!(f, "Movie end"),
+            Breakpoint::Press { port, button } => {
+                write!(f, "Port {} presses {}", port + 1, button.name())
+            }
+            Breakpoint::Marker => write!(f, "Markers"),
+        }
+    }
+}
+
+/// The breakpoints that pause the emulator.
+#[derive(Debug, Clone, Default)]
+pub struct Breakpoints {
+    breakpoints: Vec<Breakpoint>,
+    // the last frame paused on, which is not paused on again while the game lags
+    paused_on: Option<u64>,
+}
+
+impl Breakpoints {
+    /// Create an empty set of breakpoints.
+    pub fn new() -> Breakpoints {
+        Breakpoints::default()
+    }
+
+    /// Every breakpoint, in the order they were added.
+    pub fn list(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Add a breakpoint, unless it is already there.
+    pub fn add(&mut self, breakpoint: Breakpoint) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    /// Remove the breakpoint at an index in the list.
+    pub fn remove(&mut self, index: usize) {
+        if index < self.breakpoints.len() {
+            self.breakpoints.remove(index);
+        }
+    }
+
+    /// Find the first breakpoint hit before `frame` of a movie.
+    ///
+    /// Nothing is hit twice in a row on the same frame, since it stays the next frame for as
+    /// long as the game doesn't poll the controllers.
+    pub fn check(&mut self, frame: u64, movie: &Movie, playing: bool) -> Option<Breakpoint> {
+        if self.paused_on == Some(frame) {
+            return None;
+        }
+        let hit = self
+            .breakpoints
+            .iter()
+            .copied()
+            .find(|b| b.hits(frame, movie, playing))?;
+        self.paused_on = Some(frame);
+        Some(hit)
+    }
+}
+
+#[test]
+fn breakpoint_test() {
+    assert_eq!(Breakpoint::parse("120"), Some(Breakpoint::Frame(120)));
+    assert_eq!(Breakpoint::parse(" END "), Some(Breakpoint::MovieEnd));
+    assert_eq!(
+        Breakpoint::parse("2 s"),
+        Some(Breakpoint::Press {
+            port: 1,
+            button: Button::Start
+        })
+    );
+    assert_eq!(Breakpoint::parse("Marker"), Some(Breakpoint::Marker));
+    assert_eq!(Breakpoint::parse("5 S"), None);
+    assert_eq!(Breakpoint::parse("1 S 2"), None);
+
+    let mut movie = Movie::default();
+    movie.insert_empty(0, 4);
+    movie.set_button(0, Button::Start, 1..3, true);
+
+    let mut breakpoints = Breakpoints::new();
+    breakpoints.add(Breakpoint::parse("1 S").unwrap());
+    breakpoints.add(Breakpoint::MovieEnd);
+    assert_eq!(breakpoints.check(0, &movie, true), None);
+    assert!(breakpoints.check(1, &movie, true).is_some());
+    assert_eq!(breakpoints.check(1, &movie, true), None);
+    assert_eq!(breakpoints.check(2, &movie, true), None);
+    assert_eq!(breakpoints.check(4, &movie, false), None);
+    assert_eq!(
+        breakpoints.check(4, &movie, true),
+        Some(Breakpoint::MovieEnd)
+    );
+}
@@ -45,8 +45,8 @@ unsafe fn load_dynamic_procedure(
     ))
 }
 
-// load a dynamic procedure
-fn load_dynamic_lib(
+/// Load a procedure from the core library by name.
+pub fn load_dynamic_lib(
     core_lib_handle: m64p_sys::m64p_dynlib_handle,
     lib_name: &'static str,
 ) -> m64p_sys::m64p_function {
@@ -61,6 +61,7 @@ fn load_dynamic_lib(
 }
 
 /// A reference to exterior functions required for configuration.
+#[derive(Clone)]
 pub struct ConfigureFunctions {
     pub config_list_sections: m64p_sys::ptr_ConfigListSections,
     pub config_open_section: m64p_sys::ptr_ConfigOpenSection,
@@ -77,6 +78,8 @@ pub struct ConfigureFunctions {
     pub config_get_param_float: m64p_sys::ptr_ConfigGetParamFloat,
     pub config_get_param_bool: m64p_sys::ptr_ConfigGetParamBool,
     pub config_get_param_string: m64p_sys::ptr_ConfigGetParamString,
+    pub config_get_user_data_path: m64p_sys::ptr_ConfigGetUserDataPath,
+    pub config_save_section: m64p_sys::ptr_ConfigSaveSection,
 }
 
 impl ConfigureFunctions {
@@ -99,6 +102,8 @@ impl ConfigureFunctions {
             config_get_param_float: transmute(load_dynamic_lib(lib, "ConfigGetParamFloat")),
             config_get_param_bool: transmute(load_dynamic_lib(lib, "ConfigGetParamBool")),
             config_get_param_string: transmute(load_dynamic_lib(lib, "ConfigGetParamString")),
+            config_get_user_data_path: transmute(load_dynamic_lib(lib, "ConfigGetUserDataPath")),
+            config_save_section: transmute(load_dynamic_lib(lib, "ConfigSaveSection")),
         }
         }
     }
@@ -19,3 +19,26 @@
  */
 
 mod exterior;
+mod section;
+mod settings;
+
+pub use exterior::{load_dynamic_lib, ConfigureFunctions};
+pub use section::{ConfigSection, SECTION_NAME};
+pub use settings::{Settings, WindowGeometry, WindowMode};
+
+use std::{ffi::CStr, path::PathBuf};
+
+impl ConfigureFunctions {
+    /// The directory where this plugin keeps its own data files.
+    pub fn data_directory(&self) -> Option<PathBuf> {
+        let path = unsafe { (self.config_get_user_data_path?)() };
+        if path.is_null() {
+            return None;
+        }
+
+        let path = unsafe { CStr::from_ptr(path) }
+            .to_string_lossy()
+            .into_owned();
+        Some(PathBuf::from(path).join("tasinput2"))
+    }
+}
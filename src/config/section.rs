@@ -0,0 +1,190 @@
+/*
+ * src/config/section.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::ConfigureFunctions;
+use crate::state::StateError;
+use std::{
+    ffi::{c_void, CStr, CString},
+    os::raw::c_int,
+    ptr,
+};
+
+/// The name of the configuration section belonging to this plugin.
+pub const SECTION_NAME: &str = "Input-Tasinput2";
+
+/// An open section of the core's configuration file.
+pub struct ConfigSection<'a> {
+    functions: &'a ConfigureFunctions,
+    name: CString,
+    handle: m64p_sys::m64p_handle,
+}
+
+impl<'a> ConfigSection<'a> {
+    /// Open a section, creating it if it does not exist.
+    pub fn open(functions: &'a ConfigureFunctions, name: &str) -> Result<Self, StateError> {
+        let open_section = functions
+            .config_open_section
+            .ok_or(StateError::StaticMsg("ConfigOpenSection is not available"))?;
+
+        let name = CString::new(name)?;
+        let mut handle: m64p_sys::m64p_handle = ptr::null_mut();
+        match unsafe { open_section(name.as_ptr(), &mut handle) } {
+            m64p_sys::m64p_error_M64ERR_SUCCESS => Ok(ConfigSection {
+                functions,
+                name,
+                handle,
+            }),
+            e => Err(StateError::Core(e)),
+        }
+    }
+
+    /// Give a parameter a default integer value, if it does not have one already.
+    pub fn default_int(&self, param: &str, value: i32, help: &str) -> Result<(), StateError> {
+        let (param, help) = (CString::new(param)?, CString::new(help)?);
+        if let Some(set_default) = self.functions.config_set_default_int {
+            unsafe { set_default(self.handle, param.as_ptr(), value, help.as_ptr()) };
+        }
+        Ok(())
+    }
+
+    /// Give a parameter a default boolean value, if it does not have one already.
+    pub fn default_bool(&self, param: &str, value: bool, help: &str) -> Result<(), StateError> {
+        let (param, help) = (CString::new(param)?, CString::new(help)?);
+        if let Some(set_default) = self.functions.config_set_default_bool {
+            unsafe { set_default(self.handle, param.as_ptr(), value as c_int, help.as_ptr()) };
+        }
+        Ok(())
+    }
+
+    /// Give a parameter a default string value, if it does not have one already.
+    pub fn default_string(&self, param: &str, value: &str, help: &str) -> Result<(), StateError> {
+        let (param, value, help) = (
+            CString::new(param)?,
+            CString::new(value)?,
+            CString::new(help)?,
+        );
+        if let Some(set_default) = self.functions.config_set_default_string {
+            unsafe { set_default(self.handle, param.as_ptr(), value.as_ptr(), help.as_ptr()) };
+        }
+        Ok(())
+    }
+
+    /// Get the integer value of a parameter.
+    pub fn get_int(&self, param: &str) -> Result<i32, StateError> {
+        let param = CString::new(param)?;
+        let get_int = self
+            .functions
+            .config_get_param_int
+            .ok_or(StateError::StaticMsg("ConfigGetParamInt is not available"))?;
+        Ok(unsafe { get_int(self.handle, param.as_ptr()) })
+    }
+
+    /// Get the boolean value of a parameter.
+    pub fn get_bool(&self, param: &str) -> Result<bool, StateError> {
+        let param = CString::new(param)?;
+        let get_bool = self
+            .functions
+            .config_get_param_bool
+            .ok_or(StateError::StaticMsg("ConfigGetParamBool is not available"))?;
+        Ok(unsafe { get_bool(self.handle, param.as_ptr()) } != 0)
+    }
+
+    /// Get the string value of a parameter.
+    pub fn get_string(&self, param: &str) -> Result<String, StateError> {
+        let param = CString::new(param)?;
+        let get_string = self
+            .functions
+            .config_get_param_string
+            .ok_or(StateError::StaticMsg(
+                "ConfigGetParamString is not available",
+            ))?;
+
+        let value = unsafe { get_string(self.handle, param.as_ptr()) };
+        if value.is_null() {
+            Ok(String::new())
+        } else {
+            Ok(unsafe { CStr::from_ptr(value) }
+                .to_string_lossy()
+                .into_owned())
+        }
+    }
+
+    /// Set the integer value of a parameter.
+    pub fn set_int(&self, param: &str, value: i32) -> Result<(), StateError> {
+        let value: c_int = value;
+        self.set(
+            param,
+            m64p_sys::m64p_type_M64TYPE_INT,
+            &value as *const c_int as *const c_void,
+        )
+    }
+
+    /// Set the boolean value of a parameter.
+    pub fn set_bool(&self, param: &str, value: bool) -> Result<(), StateError> {
+        let value = value as c_int;
+        self.set(
+            param,
+            m64p_sys::m64p_type_M64TYPE_BOOL,
+            &value as *const c_int as *const c_void,
+        )
+    }
+
+    /// Set the string value of a parameter.
+    pub fn set_string(&self, param: &str, value: &str) -> Result<(), StateError> {
+        let value = CString::new(value)?;
+        self.set(
+            param,
+            m64p_sys::m64p_type_M64TYPE_STRING,
+            value.as_ptr() as *const c_void,
+        )
+    }
+
+    // set a parameter of any type
+    fn set(
+        &self,
+        param: &str,
+        param_type: m64p_sys::m64p_type,
+        value: *const c_void,
+    ) -> Result<(), StateError> {
+        let param = CString::new(param)?;
+        let set_parameter = self
+            .functions
+            .config_set_parameter
+            .ok_or(StateError::StaticMsg("ConfigSetParameter is not available"))?;
+
+        match unsafe { set_parameter(self.handle, param.as_ptr(), param_type, value) } {
+            m64p_sys::m64p_error_M64ERR_SUCCESS => Ok(()),
+            e => Err(StateError::Core(e)),
+        }
+    }
+
+    /// Write this section to the configuration file.
+    pub fn save(&self) -> Result<(), StateError> {
+        let save_section = self
+            .functions
+            .config_save_section
+            .ok_or(StateError::StaticMsg("ConfigSaveSection is not available"))?;
+
+        match unsafe { save_section(self.name.as_ptr()) } {
+            m64p_sys::m64p_error_M64ERR_SUCCESS => Ok(()),
+            e => Err(StateError::Core(e)),
+        }
+    }
+}
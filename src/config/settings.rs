@@ -0,0 +1,415 @@
+/*
+ * src/config/settings.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{ConfigSection, ConfigureFunctions, SECTION_NAME};
+use crate::{
+    pak::Pak, state::StateError, Device, RecordMode, Rule, RuleAction, Rules, CONTROLLER_COUNT,
+};
+
+/// How the windows for each controller port are arranged.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WindowMode {
+    /// Each port gets its own window.
+    Separate,
+    /// All ports are docked as tabs in a single window.
+    Tabbed,
+    /// A single window edits one port at a time, chosen from a dropdown.
+    Switchable,
+}
+
+impl WindowMode {
+    /// Convert the value stored in the configuration file to a window mode.
+    pub fn from_int(value: i32) -> WindowMode {
+        match value {
+            1 => WindowMode::Tabbed,
+            2 => WindowMode::Switchable,
+            _ => WindowMode::Separate,
+        }
+    }
+
+    /// Convert this window mode to the value stored in the configuration file.
+    pub fn to_int(self) -> i32 {
+        match self {
+            WindowMode::Separate => 0,
+            WindowMode::Tabbed => 1,
+            WindowMode::Switchable => 2,
+        }
+    }
+}
+
+/// The position and size of a window, and whether it stays above other windows.
+///
+/// A width or height of zero means the window has never been placed.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub always_on_top: bool,
+}
+
+impl WindowGeometry {
+    /// Tell whether this geometry has been saved before.
+    pub fn is_set(&self) -> bool {
+        self.width > 0 && self.height > 0
+    }
+
+    // register the parameters for a window with the given prefix
+    fn set_defaults(
+        section: &ConfigSection<'_>,
+        prefix: &str,
+        window: &str,
+    ) -> Result<(), StateError> {
+        for (param, what) in [
+            ("X", "X position"),
+            ("Y", "Y position"),
+            ("Width", "Width"),
+            ("Height", "Height"),
+        ]
+        .iter()
+        {
+            section.default_int(
+                &format!("{}{}", prefix, param),
+                0,
+                &format!("{} of the {}", what, window),
+            )?;
+        }
+        section.default_bool(
+            &format!("{}AlwaysOnTop", prefix),
+            false,
+            &format!("Whether the {} stays above other windows", window),
+        )
+    }
+
+    // load the parameters for a window with the given prefix
+    fn load(section: &ConfigSection<'_>, prefix: &str) -> Result<WindowGeometry, StateError> {
+        Ok(WindowGeometry {
+            x: section.get_int(&format!("{}X", prefix))?,
+            y: section.get_int(&format!("{}Y", prefix))?,
+            width: section.get_int(&format!("{}Width", prefix))?,
+            height: section.get_int(&format!("{}Height", prefix))?,
+            always_on_top: section.get_bool(&format!("{}AlwaysOnTop", prefix))?,
+        })
+    }
+
+    // save the parameters for a window with the given prefix
+    fn save(&self, section: &ConfigSection<'_>, prefix: &str) -> Result<(), StateError> {
+        section.set_int(&format!("{}X", prefix), self.x)?;
+        section.set_int(&format!("{}Y", prefix), self.y)?;
+        section.set_int(&format!("{}Width", prefix), self.width)?;
+        section.set_int(&format!("{}Height", prefix), self.height)?;
+        section.set_bool(&format!("{}AlwaysOnTop", prefix), self.always_on_top)
+    }
+}
+
+/// Settings for the plugin, stored in the core's configuration file.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub controllers: [bool; CONTROLLER_COUNT],
+    /// The device plugged into each port.
+    pub devices: [Device; CONTROLLER_COUNT],
+    /// The pak inserted into each port.
+    pub paks: [Pak; CONTROLLER_COUNT],
+    /// The Game Boy ROM inserted into the transfer pak in each port, empty if there is none.
+    pub gb_roms: [String; CONTROLLER_COUNT],
+    /// The save file of the Game Boy cartridge in each port, empty to use one next to the ROM.
+    pub gb_saves: [String; CONTROLLER_COUNT],
+    /// Whether the plugin answers controller commands itself, emulating the paks.
+    pub raw_data: bool,
+    /// Whether every controller command is written to a trace file.
+    pub trace_pif: bool,
+    /// Whether holding L, R and Start moves the stick origin, as on a real controller.
+    pub recalibration: bool,
+    /// What happens to frames with inputs a real controller cannot produce.
+    pub rules: Rules,
+    /// How a movie is recorded over.
+    pub record_mode: RecordMode,
+    /// The number of frames between greenzone states, or 0 to take none.
+    pub greenzone_interval: u64,
+    /// The most greenzone states kept at once.
+    pub greenzone_states: usize,
+    pub window_mode: WindowMode,
+    /// The window for each port, when each one gets its own window.
+    pub windows: [WindowGeometry; CONTROLLER_COUNT],
+    /// The window containing every port, when they share a window.
+    pub shared_window: WindowGeometry,
+    /// Whether the piano roll is shown.
+    pub piano_roll: bool,
+    /// The piano roll window.
+    pub piano_roll_window: WindowGeometry,
+}
+
+// the name of the parameter enabling a port
+fn controller_param(port: usize) -> String {
+    format!("Controller{}", port + 1)
+}
+
+// the name of the parameter holding a port's device
+fn device_param(port: usize) -> String {
+    format!("Port{}Device", port + 1)
+}
+
+// the name of the parameter holding a port's pak
+fn pak_param(port: usize) -> String {
+    format!("Port{}Pak", port + 1)
+}
+
+// the names of the parameters holding the cartridge in a port's transfer pak
+fn gb_rom_param(port: usize) -> String {
+    format!("Port{}GbRom", port + 1)
+}
+
+fn gb_save_param(port: usize) -> String {
+    format!("Port{}GbSave", port + 1)
+}
+
+// the prefix of the parameters for a port's window
+fn window_prefix(port: usize) -> String {
+    format!("Port{}Window", port + 1)
+}
+
+const SHARED_WINDOW_PREFIX: &str = "SharedWindow";
+const PIANO_ROLL_WINDOW_PREFIX: &str = "PianoRollWindow";
+
+impl Settings {
+    /// Load the settings from the configuration file, filling in defaults as needed.
+    pub fn load(functions: &ConfigureFunctions) -> Result<Settings, StateError> {
+        let section = ConfigSection::open(functions, SECTION_NAME)?;
+        let defaults = Settings::default();
+
+        for port in 0..CONTROLLER_COUNT {
+            section.default_bool(
+                &controller_param(port),
+                defaults.controllers[port],
+                &format!("Whether controller port {} is plugged in", port + 1),
+            )?;
+            section.default_int(
+                &device_param(port),
+                defaults.devices[port].to_int(),
+                &format!(
+                    "Device plugged into controller port {}: 0 = controller, 1 = N64 mouse, \
+                     which needs RawData",
+                    port + 1
+                ),
+            )?;
+            section.default_int(
+                &pak_param(port),
+                defaults.paks[port].to_int(),
+                &format!(
+                    "Pak inserted into controller port {}: 0 = none, 1 = controller pak, 2 = \
+                     rumble pak, 3 = transfer pak, 4 = raw",
+                    port + 1
+                ),
+            )?;
+            section.default_string(
+                &gb_rom_param(port),
+                "",
+                &format!(
+                    "Game Boy ROM in the transfer pak of controller port {}",
+                    port + 1
+                ),
+            )?;
+            section.default_string(
+                &gb_save_param(port),
+                "",
+                &format!(
+                    "Save file for the Game Boy cartridge in controller port {}, or empty to use \
+                     the ROM path with a .sav extension",
+                    port + 1
+                ),
+            )?;
+        }
+        section.default_bool(
+            "RawData",
+            defaults.raw_data,
+            "Whether the plugin answers controller commands itself instead of the core, keeping \
+             Controller Paks in its own data directory",
+        )?;
+        section.default_bool(
+            "TracePif",
+            defaults.trace_pif,
+            "Whether every controller command is logged to a trace in the data directory",
+        )?;
+        section.default_bool(
+            "Recalibration",
+            defaults.recalibration,
+            "Whether holding L, R and Start recenters the stick on its current position, as on \
+             a real controller",
+        )?;
+        for rule in Rule::ALL.iter().copied() {
+            section.default_int(
+                rule.param(),
+                defaults.rules.action(rule).to_int(),
+                &format!(
+                    "What happens to frames with {}: 0 = allow, 1 = warn, 2 = block",
+                    rule.name().to_lowercase()
+                ),
+            )?;
+        }
+        section.default_int(
+            "RecordMode",
+            defaults.record_mode.to_int(),
+            "How recording over a movie changes it: 0 = overwrite the frame, 1 = insert a new \
+             frame, 2 = XOR the pressed buttons onto the frame",
+        )?;
+        section.default_int(
+            "GreenzoneInterval",
+            defaults.greenzone_interval as i32,
+            "The number of frames between the savestates taken while a movie plays, for seeking \
+             back, or 0 to take none",
+        )?;
+        section.default_int(
+            "GreenzoneStates",
+            defaults.greenzone_states as i32,
+            "The most savestates kept for seeking back in a movie",
+        )?;
+        section.default_int(
+            "WindowMode",
+            defaults.window_mode.to_int(),
+            "How the controller windows are arranged: 0 = one window per port, 1 = tabs in one \
+             window, 2 = one window that switches between ports",
+        )?;
+        for port in 0..CONTROLLER_COUNT {
+            WindowGeometry::set_defaults(
+                &section,
+                &window_prefix(port),
+                &format!("window for port {}", port + 1),
+            )?;
+        }
+        WindowGeometry::set_defaults(&section, SHARED_WINDOW_PREFIX, "window shared by all ports")?;
+        section.default_bool(
+            "PianoRoll",
+            defaults.piano_roll,
+            "Whether the piano roll for editing movie frames is shown",
+        )?;
+        WindowGeometry::set_defaults(&section, PIANO_ROLL_WINDOW_PREFIX, "piano roll window")?;
+
+        let mut controllers = [false; CONTROLLER_COUNT];
+        for (port, enabled) in controllers.iter_mut().enumerate() {
+            *enabled = section.get_bool(&controller_param(port))?;
+        }
+
+        let mut devices = [Device::Controller; CONTROLLER_COUNT];
+        for (port, device) in devices.iter_mut().enumerate() {
+            *device = Device::from_int(section.get_int(&device_param(port))?);
+        }
+
+        let mut paks = [Pak::None; CONTROLLER_COUNT];
+        for (port, pak) in paks.iter_mut().enumerate() {
+            *pak = Pak::from_int(section.get_int(&pak_param(port))?);
+        }
+
+        let mut gb_roms: [String; CONTROLLER_COUNT] = Default::default();
+        let mut gb_saves: [String; CONTROLLER_COUNT] = Default::default();
+        for (port, (rom, save)) in gb_roms.iter_mut().zip(gb_saves.iter_mut()).enumerate() {
+            *rom = section.get_string(&gb_rom_param(port))?;
+            *save = section.get_string(&gb_save_param(port))?;
+        }
+
+        let mut windows = [WindowGeometry::default(); CONTROLLER_COUNT];
+        for (port, window) in windows.iter_mut().enumerate() {
+            *window = WindowGeometry::load(&section, &window_prefix(port))?;
+        }
+
+        let mut rules = Rules::default();
+        for rule in Rule::ALL.iter().copied() {
+            rules.set_action(rule, RuleAction::from_int(section.get_int(rule.param())?));
+        }
+
+        Ok(Settings {
+            controllers,
+            devices,
+            paks,
+            gb_roms,
+            gb_saves,
+            raw_data: section.get_bool("RawData")?,
+            trace_pif: section.get_bool("TracePif")?,
+            recalibration: section.get_bool("Recalibration")?,
+            rules,
+            record_mode: RecordMode::from_int(section.get_int("RecordMode")?),
+            greenzone_interval: section.get_int("GreenzoneInterval")?.max(0) as u64,
+            greenzone_states: section.get_int("GreenzoneStates")?.max(0) as usize,
+            window_mode: WindowMode::from_int(section.get_int("WindowMode")?),
+            windows,
+            shared_window: WindowGeometry::load(&section, SHARED_WINDOW_PREFIX)?,
+            piano_roll: section.get_bool("PianoRoll")?,
+            piano_roll_window: WindowGeometry::load(&section, PIANO_ROLL_WINDOW_PREFIX)?,
+        })
+    }
+
+    /// Write the settings to the configuration file.
+    pub fn save(&self, functions: &ConfigureFunctions) -> Result<(), StateError> {
+        let section = ConfigSection::open(functions, SECTION_NAME)?;
+
+        for (port, enabled) in self.controllers.iter().enumerate() {
+            section.set_bool(&controller_param(port), *enabled)?;
+        }
+        for (port, pak) in self.paks.iter().enumerate() {
+            section.set_int(&device_param(port), self.devices[port].to_int())?;
+            section.set_int(&pak_param(port), pak.to_int())?;
+            section.set_string(&gb_rom_param(port), &self.gb_roms[port])?;
+            section.set_string(&gb_save_param(port), &self.gb_saves[port])?;
+        }
+        section.set_bool("RawData", self.raw_data)?;
+        section.set_bool("TracePif", self.trace_pif)?;
+        section.set_bool("Recalibration", self.recalibration)?;
+        for rule in Rule::ALL.iter().copied() {
+            section.set_int(rule.param(), self.rules.action(rule).to_int())?;
+        }
+        section.set_int("RecordMode", self.record_mode.to_int())?;
+        section.set_int("GreenzoneInterval", self.greenzone_interval as i32)?;
+        section.set_int("GreenzoneStates", self.greenzone_states as i32)?;
+        section.set_int("WindowMode", self.window_mode.to_int())?;
+        for (port, window) in self.windows.iter().enumerate() {
+            window.save(&section, &window_prefix(port))?;
+        }
+        self.shared_window.save(&section, SHARED_WINDOW_PREFIX)?;
+        section.set_bool("PianoRoll", self.piano_roll)?;
+        self.piano_roll_window
+            .save(&section, PIANO_ROLL_WINDOW_PREFIX)?;
+
+        section.save()
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            controllers: [true, false, false, false],
+            devices: [Device::Controller; CONTROLLER_COUNT],
+            paks: [Pak::None; CONTROLLER_COUNT],
+            gb_roms: Default::default(),
+            gb_saves: Default::default(),
+            raw_data: false,
+            trace_pif: false,
+            recalibration: false,
+            rules: Rules::default(),
+            record_mode: RecordMode::default(),
+            greenzone_interval: 60,
+            greenzone_states: 100,
+            window_mode: WindowMode::Separate,
+            windows: [WindowGeometry::default(); CONTROLLER_COUNT],
+            shared_window: WindowGeometry::default(),
+            piano_roll: false,
+            piano_roll_window: WindowGeometry::default(),
+        }
+    }
+}
@@ -18,14 +18,13 @@
  * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use super::Checkbox;
-use crate::Inputs;
+use super::{Checkbox, InputHandle};
+use crate::Directional;
 use qt_widgets::{
     cpp_core::{CppBox, MutPtr},
     qt_core::{QString, Slot},
     QCheckBox, QHBoxLayout, QVBoxLayout, QWidget,
 };
-use std::sync::{Arc, Mutex};
 
 /// Which directional does this widget correspond to?
 pub enum DirectionalType {
@@ -37,7 +36,7 @@ pub enum DirectionalType {
 #[allow(dead_code)]
 pub struct DirectionalWidget<'a> {
     pub container: CppBox<QWidget>,
-    input_reference: &'a Arc<Mutex<Inputs>>,
+    input_reference: InputHandle<'a>,
 
     up: Checkbox,
     down: Checkbox,
@@ -52,12 +51,30 @@ pub struct DirectionalWidget<'a> {
     right_clicked: Slot<'a>,
 }
 
+/// Pointers to the checkboxes of a directional widget, used to display a directional.
+#[derive(Clone, Copy)]
+pub struct DirectionalView {
+    up: Checkbox,
+    down: Checkbox,
+    left: Checkbox,
+    right: Checkbox,
+}
+
+impl DirectionalView {
+    /// Check the boxes corresponding to a directional.
+    pub fn show(&mut self, directional: &Directional) {
+        unsafe {
+            self.up.set_checked(directional.up);
+            self.down.set_checked(directional.down);
+            self.left.set_checked(directional.left);
+            self.right.set_checked(directional.right);
+        }
+    }
+}
+
 impl<'a> DirectionalWidget<'a> {
     /// Instantiate a new directional widget.
-    pub fn new(
-        input_reference: &'a Arc<Mutex<Inputs>>,
-        dtype: DirectionalType,
-    ) -> DirectionalWidget<'a> {
+    pub fn new(input_reference: InputHandle<'a>, dtype: DirectionalType) -> DirectionalWidget<'a> {
         let mut container = unsafe { QWidget::new_0a() };
         let mut layout = unsafe { QVBoxLayout::new_1a(&mut container).into_ptr() };
 
@@ -122,4 +139,14 @@ impl<'a> DirectionalWidget<'a> {
 
         dw
     }
+
+    /// Get a view for displaying a directional in this widget.
+    pub fn view(&self) -> DirectionalView {
+        DirectionalView {
+            up: self.up,
+            down: self.down,
+            left: self.left,
+            right: self.right,
+        }
+    }
 }
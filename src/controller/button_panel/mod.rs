@@ -20,22 +20,21 @@
 
 mod directional;
 
-pub use super::Checkbox;
+pub use super::{Checkbox, InputHandle};
 
 use crate::Inputs;
-use directional::{DirectionalType, DirectionalWidget};
+use directional::{DirectionalType, DirectionalView, DirectionalWidget};
 use qt_widgets::{
     cpp_core::{CppBox, MutPtr},
     qt_core::{QString, Slot},
     QCheckBox, QHBoxLayout, QVBoxLayout, QWidget,
 };
-use std::sync::{Arc, Mutex};
 
 /// The panel used for buttons.
 #[allow(dead_code)]
 pub struct ButtonPanel<'a> {
     pub container: CppBox<QWidget>,
-    inputs: &'a Arc<Mutex<Inputs>>,
+    inputs: InputHandle<'a>,
 
     a: Checkbox,
     b: Checkbox,
@@ -57,9 +56,38 @@ pub struct ButtonPanel<'a> {
     start_clicked: Slot<'a>,
 }
 
+/// Pointers to the checkboxes of a button panel, used to display a set of inputs.
+#[derive(Clone, Copy)]
+pub struct ButtonView {
+    a: Checkbox,
+    b: Checkbox,
+    z: Checkbox,
+    l: Checkbox,
+    r: Checkbox,
+    start: Checkbox,
+    c: DirectionalView,
+    d: DirectionalView,
+}
+
+impl ButtonView {
+    /// Check the boxes corresponding to a set of inputs.
+    pub fn show(&mut self, inputs: &Inputs) {
+        unsafe {
+            self.a.set_checked(inputs.a);
+            self.b.set_checked(inputs.b);
+            self.z.set_checked(inputs.z);
+            self.l.set_checked(inputs.l);
+            self.r.set_checked(inputs.r);
+            self.start.set_checked(inputs.start);
+        }
+        self.c.show(&inputs.c);
+        self.d.show(&inputs.d);
+    }
+}
+
 impl<'a> ButtonPanel<'a> {
     /// Instantiate a new button panel.
-    pub fn new(input_reference: &'a Arc<Mutex<Inputs>>) -> ButtonPanel<'a> {
+    pub fn new(input_reference: InputHandle<'a>) -> ButtonPanel<'a> {
         let mut container = unsafe { QWidget::new_0a() };
         let mut layout = unsafe { QHBoxLayout::new_1a(&mut container).into_ptr() };
 
@@ -129,4 +157,18 @@ impl<'a> ButtonPanel<'a> {
 
         buttons
     }
+
+    /// Get a view for displaying inputs in this panel.
+    pub fn view(&self) -> ButtonView {
+        ButtonView {
+            a: self.a,
+            b: self.b,
+            z: self.z,
+            l: self.l,
+            r: self.r,
+            start: self.start,
+            c: self.c.view(),
+            d: self.d.view(),
+        }
+    }
 }
@@ -1,5 +1,5 @@
 /*
- * src/controller/joystick_panel.rs
+ * src/controller/joystick_panel/mod.rs
  * tasinput2 - Plugin for creating TAS inputs
  *
  * This file is part of tasinput2.
@@ -18,41 +18,59 @@
  * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use super::Spinbox;
-use crate::Inputs;
+mod pattern;
+mod ramp;
+
+use super::{InputHandle, RepeatGroup, Spinbox};
+use crate::{ClampMode, Inputs, PatternGenerator};
+use pattern::PatternPanel;
 use qt_widgets::{
     cpp_core::{CppBox, MutPtr},
     q_frame::Shape,
     qt_core::{QString, Slot, SlotOfInt},
     qt_gui::{QBrush, QColor, QPainter, QPen},
-    QFrame, QHBoxLayout, QLabel, QSpinBox, QVBoxLayout, QWidget,
+    QComboBox, QFrame, QHBoxLayout, QLabel, QSpinBox, QVBoxLayout, QWidget,
 };
+use ramp::RampPanel;
 use std::{
     cell::RefCell,
+    collections::VecDeque,
     convert::TryInto,
     rc::Rc,
     sync::{Arc, Mutex},
 };
 
+// the order of the entries in the clamp dropdown
+const CLAMP_MODES: [(&str, ClampMode); 3] = [
+    ("No Clamp", ClampMode::Raw),
+    ("Circle Clamp", ClampMode::Circle),
+    ("Gate Clamp", ClampMode::Gate),
+];
+
 // helper function to draw the QPicture containing the targets
 
 /// The panel allowing for manipulation of the joystick.
 #[allow(dead_code)]
 pub struct JoystickPanel<'a> {
     pub container: CppBox<QWidget>,
-    input_reference: &'a Arc<Mutex<Inputs>>,
+    input_reference: InputHandle<'a>,
 
     joystick_canvas: MutPtr<QFrame>,
 
     x: Spinbox,
     y: Spinbox,
 
+    clamp: MutPtr<QComboBox>,
+    pattern: PatternPanel<'a>,
+    ramp: RampPanel<'a>,
+
     spinbox_container: MutPtr<QWidget>,
     x_container: MutPtr<QWidget>,
     y_container: MutPtr<QWidget>,
 
     x_changed: SlotOfInt<'a>,
     y_changed: SlotOfInt<'a>,
+    clamp_changed: SlotOfInt<'a>,
 
     //    canvas_clicked: Slot<'a>,
     //    canvas_dragged: Slot<'a>,
@@ -61,9 +79,31 @@ pub struct JoystickPanel<'a> {
     y_value: Rc<RefCell<i8>>,
 }
 
+/// Pointers to the spinboxes of a joystick panel, used to display a stick position.
+#[derive(Clone, Copy)]
+pub struct JoystickView {
+    x: Spinbox,
+    y: Spinbox,
+}
+
+impl JoystickView {
+    /// Show the stick position of a set of inputs.
+    pub fn show(&mut self, inputs: &Inputs) {
+        unsafe {
+            self.x.set_value(inputs.x.into());
+            self.y.set_value(inputs.y.into());
+        }
+    }
+}
+
 impl<'a> JoystickPanel<'a> {
     /// Instantiate a new joystick panel.
-    pub fn new(input_reference: &'a Arc<Mutex<Inputs>>) -> JoystickPanel<'a> {
+    pub fn new(
+        input_reference: InputHandle<'a>,
+        pattern_reference: &'a Arc<Mutex<Option<PatternGenerator>>>,
+        queue_reference: &'a Arc<Mutex<VecDeque<Inputs>>>,
+        clamp_reference: &'a Arc<Mutex<ClampMode>>,
+    ) -> JoystickPanel<'a> {
         let mut container = unsafe { QWidget::new_0a() };
         let mut layout = unsafe { QHBoxLayout::new_1a(&mut container).into_ptr() };
 
@@ -83,6 +123,23 @@ impl<'a> JoystickPanel<'a> {
         let mut spinbox_layout = unsafe { QVBoxLayout::new_1a(&mut spinbox_container).into_ptr() };
         let (mut x_container, mut x) = spinbox!("X", spinbox_layout);
         let (mut y_container, mut y) = spinbox!("Y", spinbox_layout);
+
+        // dropdown for the clamp mode
+        let mut clamp = unsafe {
+            let mut clamp = QComboBox::new_0a();
+            for (name, _) in CLAMP_MODES.iter() {
+                clamp.add_item_q_string(&QString::from_std_str(name));
+            }
+            spinbox_layout.add_widget(&mut clamp);
+            clamp.into_ptr()
+        };
+
+        // create the pattern generator and ramp controls below the spinboxes
+        let mut pattern = PatternPanel::new(input_reference, pattern_reference);
+        unsafe { spinbox_layout.add_widget(pattern.container.as_mut_ptr()) };
+        let mut ramp = RampPanel::new(input_reference, queue_reference, clamp_reference);
+        unsafe { spinbox_layout.add_widget(ramp.container.as_mut_ptr()) };
+
         unsafe { layout.add_widget(&mut spinbox_container) };
         let spinbox_container = unsafe { spinbox_container.into_ptr() };
 
@@ -93,10 +150,11 @@ impl<'a> JoystickPanel<'a> {
         let x_ref = x_value.clone();
         let y_ref = y_value.clone();
 
-        // updater function
+        // updater function, where holding a spinbox arrow is undone in one step
+        let (x_group, y_group) = (RepeatGroup::default(), RepeatGroup::default());
         let mut update_x = move |xval: i8| {
-            let mut input = input_reference.lock().unwrap();
-            input.x = xval;
+            let group = x_group.group(&input_reference);
+            input_reference.edit(Some(group), |input| input.x = xval);
 
             unsafe {
                 x.set_value(xval.into());
@@ -106,8 +164,8 @@ impl<'a> JoystickPanel<'a> {
         };
 
         let mut update_y = move |yval: i8| {
-            let mut input = input_reference.lock().unwrap();
-            input.y = yval;
+            let group = y_group.group(&input_reference);
+            input_reference.edit(Some(group), |input| input.y = yval);
 
             unsafe {
                 y.set_value(yval.into());
@@ -145,12 +203,23 @@ impl<'a> JoystickPanel<'a> {
             })
         };
 
+        let clamp_changed = unsafe {
+            SlotOfInt::new(move |index| {
+                let (_, mode) = CLAMP_MODES[index.max(0) as usize];
+                *clamp_reference.lock().unwrap() = mode;
+            })
+        };
+
         let mut jp = JoystickPanel {
             container,
             input_reference,
 
             joystick_canvas,
 
+            clamp,
+            pattern,
+            ramp,
+
             spinbox_container,
             x,
             y,
@@ -162,15 +231,25 @@ impl<'a> JoystickPanel<'a> {
 
             x_changed,
             y_changed,
+            clamp_changed,
             //canvas_update,
         };
 
         unsafe {
             x.value_changed().connect(&jp.x_changed);
             y.value_changed().connect(&jp.y_changed);
+            clamp.current_index_changed().connect(&jp.clamp_changed);
             //joystick_canvas.slot_update().connect(&jp.canvas_update);
         };
 
         jp
     }
+
+    /// Get a view for displaying the stick position in this panel.
+    pub fn view(&self) -> JoystickView {
+        JoystickView {
+            x: self.x,
+            y: self.y,
+        }
+    }
 }
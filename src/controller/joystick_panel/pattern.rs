@@ -0,0 +1,235 @@
+/*
+ * src/controller/joystick_panel/pattern.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{InputHandle, RepeatGroup, Spinbox};
+use crate::{PatternGenerator, StickPattern};
+use qt_widgets::{
+    cpp_core::{CppBox, MutPtr},
+    qt_core::{QString, Slot, SlotOfInt},
+    QComboBox, QHBoxLayout, QLabel, QPushButton, QSpinBox, QVBoxLayout, QWidget,
+};
+use std::{
+    cell::RefCell,
+    convert::TryInto,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+// the order of the entries in the pattern dropdown
+const PATTERN_NAMES: [&str; 4] = ["No Pattern", "Spin", "Oscillate", "Alternate"];
+
+// build a pattern from the values entered into the panel
+fn build_pattern(
+    mode: i32,
+    radius: i32,
+    speed: i32,
+    frames: i32,
+    positions: &[(i8, i8)],
+) -> Option<StickPattern> {
+    let frames: u32 = frames.try_into().unwrap_or(1);
+
+    match mode {
+        1 => Some(StickPattern::Spin {
+            radius: f64::from(radius),
+            speed: f64::from(speed),
+            start_angle: 0.0,
+        }),
+        2 if positions.len() >= 2 => Some(StickPattern::Oscillate {
+            from: positions[0],
+            to: positions[1],
+            period: frames,
+        }),
+        3 if !positions.is_empty() => Some(StickPattern::Alternate {
+            positions: positions.to_vec(),
+            hold: frames,
+        }),
+        _ => None,
+    }
+}
+
+/// Controls for driving the joystick with a stick pattern.
+#[allow(dead_code)]
+pub struct PatternPanel<'a> {
+    pub container: CppBox<QWidget>,
+    pattern_reference: &'a Arc<Mutex<Option<PatternGenerator>>>,
+
+    mode: MutPtr<QComboBox>,
+    radius: Spinbox,
+    speed: Spinbox,
+    frames: Spinbox,
+    store: MutPtr<QPushButton>,
+    clear: MutPtr<QPushButton>,
+    stored_label: MutPtr<QLabel>,
+
+    radius_container: MutPtr<QWidget>,
+    speed_container: MutPtr<QWidget>,
+    frames_container: MutPtr<QWidget>,
+    button_container: MutPtr<QWidget>,
+
+    mode_changed: SlotOfInt<'a>,
+    value_changed: SlotOfInt<'a>,
+    store_clicked: Slot<'a>,
+    clear_clicked: Slot<'a>,
+
+    positions: Rc<RefCell<Vec<(i8, i8)>>>,
+}
+
+impl<'a> PatternPanel<'a> {
+    /// Instantiate a new pattern panel.
+    pub fn new(
+        input_reference: InputHandle<'a>,
+        pattern_reference: &'a Arc<Mutex<Option<PatternGenerator>>>,
+    ) -> PatternPanel<'a> {
+        let mut container = unsafe { QWidget::new_0a() };
+        let mut layout = unsafe { QVBoxLayout::new_1a(&mut container).into_ptr() };
+
+        // dropdown for the type of pattern
+        let mut mode = unsafe {
+            let mut mode = QComboBox::new_0a();
+            for name in PATTERN_NAMES.iter() {
+                mode.add_item_q_string(&QString::from_std_str(name));
+            }
+            layout.add_widget(&mut mode);
+            mode.into_ptr()
+        };
+
+        // parameters for the pattern
+        let (radius_container, mut radius) = spinbox!("Radius", layout);
+        let (speed_container, mut speed) = spinbox!("Degrees/Frame", layout);
+        let (frames_container, mut frames) = spinbox!("Frames", layout);
+        unsafe {
+            radius.set_range(0, 127);
+            radius.set_value(127);
+            speed.set_range(-180, 180);
+            speed.set_value(45);
+            frames.set_range(1, 600);
+            frames.set_value(2);
+        }
+
+        // buttons for storing positions used by oscillate and alternate
+        let mut button_container = unsafe { QWidget::new_0a() };
+        let mut button_layout = unsafe { QHBoxLayout::new_1a(&mut button_container).into_ptr() };
+        let (mut store, mut clear, button_container) = unsafe {
+            let mut store = QPushButton::from_q_string(&QString::from_std_str("Store Position"));
+            let mut clear = QPushButton::from_q_string(&QString::from_std_str("Clear"));
+            button_layout.add_widget(&mut store);
+            button_layout.add_widget(&mut clear);
+            layout.add_widget(&mut button_container);
+            (
+                store.into_ptr(),
+                clear.into_ptr(),
+                button_container.into_ptr(),
+            )
+        };
+        let mut stored_label = unsafe {
+            let mut label = QLabel::from_q_string(&QString::from_std_str("0 positions stored"));
+            layout.add_widget(&mut label);
+            label.into_ptr()
+        };
+
+        let positions = Rc::new(RefCell::new(Vec::new()));
+
+        // rebuild the pattern whenever anything in the panel changes, where holding a spinbox
+        // arrow is undone in one step
+        let positions_ref = positions.clone();
+        let repeat_group = RepeatGroup::default();
+        let rebuild = Rc::new(move || {
+            let pattern = unsafe {
+                build_pattern(
+                    mode.current_index(),
+                    radius.value(),
+                    speed.value(),
+                    frames.value(),
+                    &positions_ref.borrow(),
+                )
+            };
+            let group = repeat_group.group(&input_reference);
+            input_reference.edit_pattern(Some(group), |generator| {
+                *generator = pattern.map(PatternGenerator::new)
+            });
+
+            unsafe {
+                stored_label.set_text(&QString::from_std_str(format!(
+                    "{} positions stored",
+                    positions_ref.borrow().len()
+                )))
+            };
+        });
+
+        let rebuild_ref = rebuild.clone();
+        let mode_changed = unsafe { SlotOfInt::new(move |_| rebuild_ref()) };
+        let rebuild_ref = rebuild.clone();
+        let value_changed = unsafe { SlotOfInt::new(move |_| rebuild_ref()) };
+
+        let positions_ref = positions.clone();
+        let rebuild_ref = rebuild.clone();
+        let store_clicked = unsafe {
+            Slot::new(move || {
+                let inputs = input_reference.get();
+                positions_ref.borrow_mut().push((inputs.x, inputs.y));
+                rebuild_ref();
+            })
+        };
+
+        let positions_ref = positions.clone();
+        let clear_clicked = unsafe {
+            Slot::new(move || {
+                positions_ref.borrow_mut().clear();
+                rebuild();
+            })
+        };
+
+        let pp = PatternPanel {
+            container,
+            pattern_reference,
+
+            mode,
+            radius,
+            speed,
+            frames,
+            store,
+            clear,
+            stored_label,
+
+            radius_container,
+            speed_container,
+            frames_container,
+            button_container,
+
+            mode_changed,
+            value_changed,
+            store_clicked,
+            clear_clicked,
+
+            positions,
+        };
+
+        unsafe {
+            mode.current_index_changed().connect(&pp.mode_changed);
+            radius.value_changed().connect(&pp.value_changed);
+            speed.value_changed().connect(&pp.value_changed);
+            frames.value_changed().connect(&pp.value_changed);
+            store.clicked().connect(&pp.store_clicked);
+            clear.clicked().connect(&pp.clear_clicked);
+        };
+
+        pp
+    }
+}
@@ -0,0 +1,175 @@
+/*
+ * src/controller/joystick_panel/ramp.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{InputHandle, Spinbox};
+use crate::{state::queue_ramp, ClampMode, Inputs, Interpolation, Keyframe, Ramp};
+use qt_widgets::{
+    cpp_core::{CppBox, MutPtr},
+    qt_core::{QString, Slot},
+    QComboBox, QHBoxLayout, QLabel, QPushButton, QSpinBox, QVBoxLayout, QWidget,
+};
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+/// Controls for queueing a stick ramp from a stored keyframe to the current stick position.
+#[allow(dead_code)]
+pub struct RampPanel<'a> {
+    pub container: CppBox<QWidget>,
+    queue_reference: &'a Arc<Mutex<VecDeque<Inputs>>>,
+
+    interpolation: MutPtr<QComboBox>,
+    frames: Spinbox,
+    set_start: MutPtr<QPushButton>,
+    queue: MutPtr<QPushButton>,
+    clear: MutPtr<QPushButton>,
+    status: MutPtr<QLabel>,
+
+    frames_container: MutPtr<QWidget>,
+    button_container: MutPtr<QWidget>,
+
+    set_start_clicked: Slot<'a>,
+    queue_clicked: Slot<'a>,
+    clear_clicked: Slot<'a>,
+
+    start: Rc<RefCell<(i8, i8)>>,
+}
+
+impl<'a> RampPanel<'a> {
+    /// Instantiate a new ramp panel.
+    pub fn new(
+        input_reference: InputHandle<'a>,
+        queue_reference: &'a Arc<Mutex<VecDeque<Inputs>>>,
+        clamp_reference: &'a Arc<Mutex<ClampMode>>,
+    ) -> RampPanel<'a> {
+        let mut container = unsafe { QWidget::new_0a() };
+        let mut layout = unsafe { QVBoxLayout::new_1a(&mut container).into_ptr() };
+
+        // dropdown for the interpolation
+        let mut interpolation = unsafe {
+            let mut interpolation = QComboBox::new_0a();
+            for interp in Interpolation::ALL.iter() {
+                interpolation.add_item_q_string(&QString::from_std_str(interp.name()));
+            }
+            layout.add_widget(&mut interpolation);
+            interpolation.into_ptr()
+        };
+
+        let (frames_container, mut frames) = spinbox!("Ramp Frames", layout);
+        unsafe {
+            frames.set_range(1, 600);
+            frames.set_value(10);
+        }
+
+        let mut button_container = unsafe { QWidget::new_0a() };
+        let mut button_layout = unsafe { QHBoxLayout::new_1a(&mut button_container).into_ptr() };
+        let (mut set_start, mut queue, mut clear, button_container) = unsafe {
+            let mut set_start = QPushButton::from_q_string(&QString::from_std_str("Set Start"));
+            let mut queue = QPushButton::from_q_string(&QString::from_std_str("Queue Ramp"));
+            let mut clear = QPushButton::from_q_string(&QString::from_std_str("Clear Queue"));
+            button_layout.add_widget(&mut set_start);
+            button_layout.add_widget(&mut queue);
+            button_layout.add_widget(&mut clear);
+            layout.add_widget(&mut button_container);
+            (
+                set_start.into_ptr(),
+                queue.into_ptr(),
+                clear.into_ptr(),
+                button_container.into_ptr(),
+            )
+        };
+
+        let mut status = unsafe {
+            let mut label = QLabel::from_q_string(&QString::from_std_str("Start: (0, 0)"));
+            layout.add_widget(&mut label);
+            label.into_ptr()
+        };
+
+        let start = Rc::new(RefCell::new((0, 0)));
+
+        let start_ref = start.clone();
+        let set_start_clicked = unsafe {
+            Slot::new(move || {
+                let inputs = input_reference.get();
+                *start_ref.borrow_mut() = (inputs.x, inputs.y);
+                status.set_text(&QString::from_std_str(format!(
+                    "Start: ({}, {})",
+                    inputs.x, inputs.y
+                )));
+            })
+        };
+
+        // ramp from the stored start on the next frame to the current stick position
+        let start_ref = start.clone();
+        let queue_clicked = unsafe {
+            Slot::new(move || {
+                let inputs = input_reference.get();
+                let (x, y) = *start_ref.borrow();
+                let interp = Interpolation::ALL[interpolation.current_index().max(0) as usize];
+
+                let ramp = Ramp::new(
+                    Keyframe::new(0, x, y),
+                    Keyframe::new(frames.value() as u64 - 1, inputs.x, inputs.y),
+                    interp,
+                    *clamp_reference.lock().unwrap(),
+                );
+                input_reference.edit_queue(None, |queue| queue_ramp(queue, inputs, &ramp));
+            })
+        };
+
+        let clear_clicked = unsafe {
+            Slot::new(move || {
+                input_reference.edit_queue(None, VecDeque::clear);
+            })
+        };
+
+        let rp = RampPanel {
+            container,
+            queue_reference,
+
+            interpolation,
+            frames,
+            set_start,
+            queue,
+            clear,
+            status,
+
+            frames_container,
+            button_container,
+
+            set_start_clicked,
+            queue_clicked,
+            clear_clicked,
+
+            start,
+        };
+
+        unsafe {
+            set_start.clicked().connect(&rp.set_start_clicked);
+            queue.clicked().connect(&rp.queue_clicked);
+            clear.clicked().connect(&rp.clear_clicked);
+        };
+
+        rp
+    }
+}
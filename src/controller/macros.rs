@@ -36,14 +36,16 @@ macro_rules! clicked_handler {
     ($inputs: ident, $cbox: ident) => {
         unsafe {
             Slot::new(move || {
-                (*$inputs.lock().unwrap()).$cbox = $cbox.is_checked();
+                let checked = $cbox.is_checked();
+                $inputs.edit(None, move |inputs| inputs.$cbox = checked);
             })
         }
     };
     ($inputs: ident, $cbox: ident, $dname: ident.$pname: ident) => {
         unsafe {
             Slot::new(move || {
-                (*$inputs.lock().unwrap()).$dname.$pname = $cbox.is_checked();
+                let checked = $cbox.is_checked();
+                $inputs.edit(None, move |inputs| inputs.$dname.$pname = checked);
             })
         }
     };
@@ -22,47 +22,349 @@
 mod macros;
 mod button_panel;
 mod joystick_panel;
+mod mouse_panel;
+mod slot_bar;
 
-use crate::Inputs;
-use button_panel::ButtonPanel;
-use joystick_panel::JoystickPanel;
+use crate::{pak::Pak, state::SharedState, Device, Inputs, PatternGenerator};
+use button_panel::{ButtonPanel, ButtonView};
+use joystick_panel::{JoystickPanel, JoystickView};
+use mouse_panel::{MousePanel, MouseView};
 use qt_widgets::{
     cpp_core::{CppBox, MutPtr},
-    qt_core::{QString, Slot},
-    QCheckBox, QHBoxLayout, QLabel, QSpinBox, QVBoxLayout, QWidget,
+    qt_core::{QString, QTimer, Slot, SlotOfInt, WindowType},
+    qt_gui::QKeySequence,
+    QCheckBox, QComboBox, QHBoxLayout, QLabel, QShortcut, QSpinBox, QVBoxLayout, QWidget,
 };
+use slot_bar::SlotBar;
 use std::{
+    cell::Cell,
+    collections::VecDeque,
     convert::TryInto,
-    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 pub type Checkbox = MutPtr<QCheckBox>;
 pub type Spinbox = MutPtr<QSpinBox>;
 
+// changes further apart than this are undone separately
+const REPEAT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The inputs set in the GUI for a port, changed through the undo history.
+#[derive(Clone, Copy)]
+pub struct InputHandle<'a> {
+    shared: &'a SharedState,
+    port: usize,
+}
+
+impl<'a> InputHandle<'a> {
+    /// The inputs currently set.
+    pub fn get(&self) -> Inputs {
+        *self.shared.inputs[self.port].lock().unwrap()
+    }
+
+    /// Change the inputs, undone together with the previous change if it is in the same group.
+    pub fn edit<F: FnOnce(&mut Inputs)>(&self, group: Option<u64>, edit: F) {
+        self.shared.edit_inputs(self.port, group, edit);
+    }
+
+    /// Change the stick pattern driving the port.
+    pub fn edit_pattern<F: FnOnce(&mut Option<PatternGenerator>)>(
+        &self,
+        group: Option<u64>,
+        edit: F,
+    ) {
+        self.shared.edit_pattern(self.port, group, edit);
+    }
+
+    /// Change the frames queued for the port.
+    pub fn edit_queue<F: FnOnce(&mut VecDeque<Inputs>)>(&self, group: Option<u64>, edit: F) {
+        self.shared.edit_queue(self.port, group, edit);
+    }
+
+    /// Get a new group for changes that are undone together.
+    pub fn group(&self) -> u64 {
+        self.shared.history.lock().unwrap().group()
+    }
+}
+
+/// Puts a run of changes made in quick succession on the same frame, like holding a spinbox
+/// arrow, into one undo step.
+#[derive(Default)]
+pub struct RepeatGroup {
+    // the group of the last change, with the frame and time it was made at
+    last: Cell<Option<(u64, u64, Instant)>>,
+}
+
+impl RepeatGroup {
+    /// The group for a change made now, a new one if the last change was a while ago.
+    pub fn group(&self, inputs: &InputHandle) -> u64 {
+        let frame = *inputs.shared.frame.lock().unwrap();
+        let now = Instant::now();
+        let group = match self.last.get() {
+            Some((group, last_frame, last))
+                if last_frame == frame && now - last < REPEAT_INTERVAL =>
+            {
+                group
+            }
+            _ => inputs.group(),
+        };
+        self.last.set(Some((group, frame, now)));
+        group
+    }
+}
+
+/// Pointers to every widget that displays part of the inputs.
+#[derive(Clone, Copy)]
+pub struct InputView {
+    buttons: ButtonView,
+    joystick: Option<JoystickView>,
+    mouse: Option<MouseView>,
+}
+
+impl InputView {
+    /// Update the widgets to show a set of inputs.
+    pub fn show(&mut self, inputs: &Inputs) {
+        self.buttons.show(inputs);
+        if let Some(ref mut joystick) = self.joystick {
+            joystick.show(inputs);
+        }
+        if let Some(ref mut mouse) = self.mouse {
+            mouse.show(inputs);
+        }
+    }
+}
+
 /// Represents a window used to control inputs.
 #[allow(dead_code)]
 pub struct Controller<'a> {
     base_window: CppBox<QWidget>,
-    inputs: &'a Arc<Mutex<Inputs>>,
+    inputs: InputHandle<'a>,
 
     buttons: ButtonPanel<'a>,
-    joystick: JoystickPanel<'a>,
+    joystick: Option<JoystickPanel<'a>>,
+    mouse: Option<MousePanel<'a>>,
+    slot_bar: SlotBar<'a>,
+
+    plugged: Checkbox,
+    plugged_clicked: Slot<'a>,
+    pak: MutPtr<QComboBox>,
+    pak_changed: SlotOfInt<'a>,
+    rumble: MutPtr<QLabel>,
+    origin: MutPtr<QLabel>,
+    broken_rules: MutPtr<QLabel>,
+    marker: MutPtr<QLabel>,
+    status_timer: CppBox<QTimer>,
+    status_update: Slot<'a>,
+    undo_shortcut: MutPtr<QShortcut>,
+    redo_shortcut: MutPtr<QShortcut>,
+    undo_activated: Slot<'a>,
+    redo_activated: Slot<'a>,
+
+    always_on_top: Checkbox,
+    always_on_top_clicked: Slot<'a>,
 }
 
 impl<'a> Controller<'a> {
-    /// Instantiate a new controller
-    pub fn new(input_reference: &'a Arc<Mutex<Inputs>>) -> Controller<'a> {
+    /// Instantiate a new controller for the device in a port.
+    pub fn new(port: usize, device: Device, shared: &'a SharedState) -> Controller<'a> {
+        let input_reference = InputHandle { shared, port };
+
         let mut base_window = unsafe { QWidget::new_0a() };
-        unsafe { base_window.set_window_title(&QString::from_std_str("TAS Input")) };
+        unsafe {
+            base_window.set_window_title(&QString::from_std_str(format!(
+                "TAS Input - Port {}",
+                port + 1
+            )))
+        };
         let mut layout = unsafe { QVBoxLayout::new_1a(&mut base_window).into_ptr() };
 
-        let mut joystick = JoystickPanel::new(input_reference);
-        unsafe { layout.add_widget(joystick.container.as_mut_ptr()) };
+        // a mouse only has two buttons, shown alongside its motion instead of a stick
+        let (mut joystick, mut mouse) = match device {
+            Device::Controller => (
+                Some(JoystickPanel::new(
+                    input_reference,
+                    &shared.patterns[port],
+                    &shared.queues[port],
+                    &shared.clamp,
+                )),
+                None,
+            ),
+            Device::Mouse => (None, Some(MousePanel::new(input_reference))),
+        };
+        if let Some(ref mut joystick) = joystick {
+            unsafe { layout.add_widget(joystick.container.as_mut_ptr()) };
+        }
+        if let Some(ref mut mouse) = mouse {
+            unsafe { layout.add_widget(mouse.container.as_mut_ptr()) };
+        }
 
         let mut buttons = ButtonPanel::new(input_reference);
-        unsafe { layout.add_widget(buttons.container.as_mut_ptr()) };
+        unsafe {
+            layout.add_widget(buttons.container.as_mut_ptr());
+            buttons.container.set_visible(device == Device::Controller);
+        }
+
+        let view = InputView {
+            buttons: buttons.view(),
+            joystick: joystick.as_ref().map(|j| j.view()),
+            mouse: mouse.as_ref().map(|m| m.view()),
+        };
+        let mut slot_bar = SlotBar::new(input_reference, &shared.slots, view, unsafe {
+            base_window.as_mut_ptr()
+        });
+        unsafe { layout.add_widget(slot_bar.container.as_mut_ptr()) };
+
+        // ctrl + z undoes the last edit to any port or the movie, ctrl + shift + z redoes it
+        let window_ptr = unsafe { base_window.as_mut_ptr() };
+        let shortcut = |sequence: &str| unsafe {
+            QShortcut::new_2a(
+                &QKeySequence::from_q_string(&QString::from_std_str(sequence)),
+                window_ptr,
+            )
+            .into_ptr()
+        };
+        let undo_shortcut = shortcut("Ctrl+Z");
+        let redo_shortcut = shortcut("Ctrl+Shift+Z");
+        let undo_activated = unsafe {
+            Slot::new(move || {
+                shared.undo();
+            })
+        };
+        let redo_activated = unsafe {
+            Slot::new(move || {
+                shared.redo();
+            })
+        };
+
+        // plug the controller and its pak in or out, applied on the next frame
+        let paks_reference = &shared.paks;
+        let plugged_reference = &shared.plugged;
+        let mut pak_container = unsafe { QWidget::new_0a() };
+        let mut pak_layout = unsafe { QHBoxLayout::new_1a(&mut pak_container).into_ptr() };
+        let mut plugged = checkbox!("Plugged In", pak_layout);
+        unsafe { plugged.set_checked(plugged_reference.lock().unwrap()[port]) };
+        let plugged_clicked = unsafe {
+            Slot::new(move || {
+                plugged_reference.lock().unwrap()[port] = plugged.is_checked();
+            })
+        };
+        let (pak, rumble, origin, broken_rules, marker) = unsafe {
+            let mut pak_label = QLabel::from_q_string(&QString::from_std_str("Pak"));
+            let mut pak = QComboBox::new_0a();
+            for p in Pak::ALL.iter() {
+                pak.add_item_q_string(&QString::from_std_str(p.name()));
+            }
+            pak.set_current_index(paks_reference.lock().unwrap()[port].to_int());
+            pak.set_enabled(device.has_pak_slot());
+            let mut rumble = QLabel::from_q_string(&QString::from_std_str(""));
+            let mut origin = QLabel::from_q_string(&QString::from_std_str(""));
+            let mut broken_rules = QLabel::from_q_string(&QString::from_std_str(""));
+            let mut marker = QLabel::from_q_string(&QString::from_std_str(""));
+            pak_layout.add_widget(&mut pak_label);
+            pak_layout.add_widget(&mut pak);
+            pak_layout.add_widget(&mut rumble);
+            pak_layout.add_widget(&mut origin);
+            pak_layout.add_widget(&mut broken_rules);
+            pak_layout.add_widget(&mut marker);
+            layout.add_widget(pak_container.into_ptr());
+            pak_label.into_ptr();
+            (
+                pak.into_ptr(),
+                rumble.into_ptr(),
+                origin.into_ptr(),
+                broken_rules.into_ptr(),
+                marker.into_ptr(),
+            )
+        };
+        let pak_changed = unsafe {
+            SlotOfInt::new(move |index| {
+                paks_reference.lock().unwrap()[port] = Pak::from_int(index);
+            })
+        };
+
+        // show whether the rumble pak is running, where the stick origin was moved to, which rules
+        // the last frame broke and the last marker reached in the movie, polling since all of them
+        // are driven by the emulator; the inputs are shown again when an undo or redo might have
+        // changed them, and the port when a movie plugged in something else
+        let mut status_view = view;
+        let seen_revision = Cell::new(shared.revision());
+        let rumble_reference = &shared.rumble[port];
+        let origins_reference = &shared.origins;
+        let broken_rules_reference = &shared.broken_rules;
+        let movie_reference = &shared.movie;
+        let frame_reference = &shared.frame;
+        let mut rumble_label = rumble;
+        let mut origin_label = origin;
+        let mut broken_rules_label = broken_rules;
+        let mut marker_label = marker;
+        let mut plugged_check = plugged;
+        let mut pak_select = pak;
+        let status_update = unsafe {
+            Slot::new(move || {
+                let revision = shared.revision();
+                if seen_revision.replace(revision) != revision {
+                    status_view.show(&input_reference.get());
+                }
+
+                let present = plugged_reference.lock().unwrap()[port];
+                if plugged_check.is_checked() != present {
+                    plugged_check.set_checked(present);
+                }
+                let pak = paks_reference.lock().unwrap()[port].to_int();
+                if pak_select.current_index() != pak {
+                    pak_select.set_current_index(pak);
+                }
+
+                let text = if *rumble_reference.lock().unwrap() {
+                    "Rumbling"
+                } else {
+                    ""
+                };
+                rumble_label.set_text(&QString::from_std_str(text));
+
+                let origin = origins_reference.lock().unwrap()[port];
+                let text = if (origin.x, origin.y) == (0, 0) {
+                    String::new()
+                } else {
+                    format!("Origin {}, {}", origin.x, origin.y)
+                };
+                origin_label.set_text(&QString::from_std_str(text));
 
-        unsafe { base_window.show() };
+                let names: Vec<_> = broken_rules_reference.lock().unwrap()[port]
+                    .iter()
+                    .map(|rule| rule.name())
+                    .collect();
+                broken_rules_label.set_text(&QString::from_std_str(names.join(", ")));
+
+                let frame = *frame_reference.lock().unwrap();
+                let text = match movie_reference.lock().unwrap().markers.current(frame) {
+                    Some(marker) if marker.comment.is_empty() => marker.name.clone(),
+                    Some(marker) if marker.name.is_empty() => marker.comment.clone(),
+                    Some(marker) => format!("{}: {}", marker.name, marker.comment),
+                    None => String::new(),
+                };
+                marker_label.set_text(&QString::from_std_str(text));
+            })
+        };
+        let mut status_timer = unsafe { QTimer::new_0a() };
+
+        // keep whichever window this controller ends up in above the emulator
+        let always_on_top = checkbox!("Always on Top", layout);
+        let mut window_ptr = unsafe { base_window.as_mut_ptr() };
+        let always_on_top_clicked = unsafe {
+            Slot::new(move || {
+                let mut top = window_ptr.window();
+                top.set_window_flag_2a(
+                    WindowType::WindowStaysOnTopHint,
+                    always_on_top.is_checked(),
+                );
+
+                // changing the window flags hides the window
+                top.show();
+            })
+        };
+
+        unsafe { status_timer.start_1a(50) };
 
         let controller = Controller {
             base_window,
@@ -70,8 +372,63 @@ impl<'a> Controller<'a> {
 
             buttons,
             joystick,
+            mouse,
+            slot_bar,
+
+            plugged,
+            plugged_clicked,
+            pak,
+            pak_changed,
+            rumble,
+            origin,
+            broken_rules,
+            marker,
+            status_timer,
+            status_update,
+            undo_shortcut,
+            redo_shortcut,
+            undo_activated,
+            redo_activated,
+
+            always_on_top,
+            always_on_top_clicked,
+        };
+
+        unsafe {
+            plugged.clicked().connect(&controller.plugged_clicked);
+            pak.current_index_changed().connect(&controller.pak_changed);
+            controller
+                .status_timer
+                .timeout()
+                .connect(&controller.status_update);
+            undo_shortcut
+                .activated()
+                .connect(&controller.undo_activated);
+            redo_shortcut
+                .activated()
+                .connect(&controller.redo_activated);
+            always_on_top
+                .clicked()
+                .connect(&controller.always_on_top_clicked)
         };
 
         controller
     }
+
+    /// Update the always on top checkbox to match the window this controller is in.
+    pub fn refresh_always_on_top(&mut self) {
+        unsafe {
+            let on_top = self
+                .base_window
+                .window()
+                .window_flags()
+                .test_flag(WindowType::WindowStaysOnTopHint);
+            self.always_on_top.set_checked(on_top);
+        }
+    }
+
+    /// The widget containing this controller, to be shown on its own or docked elsewhere.
+    pub fn window(&mut self) -> MutPtr<QWidget> {
+        unsafe { self.base_window.as_mut_ptr() }
+    }
 }
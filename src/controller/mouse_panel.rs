@@ -0,0 +1,150 @@
+/*
+ * src/controller/mouse_panel.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{Checkbox, InputHandle, RepeatGroup, Spinbox};
+use crate::Inputs;
+use qt_widgets::{
+    cpp_core::CppBox,
+    qt_core::{QString, Slot, SlotOfInt},
+    QCheckBox, QHBoxLayout, QLabel, QSpinBox, QVBoxLayout, QWidget,
+};
+use std::convert::TryInto;
+
+/// The panel used in place of the joystick and buttons for an N64 Mouse.
+///
+/// The mouse reports its buttons as A and B, and the distance it moved this frame on the
+/// stick axes.
+#[allow(dead_code)]
+pub struct MousePanel<'a> {
+    pub container: CppBox<QWidget>,
+    inputs: InputHandle<'a>,
+
+    left: Checkbox,
+    right: Checkbox,
+    x: Spinbox,
+    y: Spinbox,
+
+    left_clicked: Slot<'a>,
+    right_clicked: Slot<'a>,
+    x_changed: SlotOfInt<'a>,
+    y_changed: SlotOfInt<'a>,
+}
+
+/// Pointers to the widgets of a mouse panel, used to display a set of inputs.
+#[derive(Clone, Copy)]
+pub struct MouseView {
+    left: Checkbox,
+    right: Checkbox,
+    x: Spinbox,
+    y: Spinbox,
+}
+
+impl MouseView {
+    /// Show the buttons and motion of a set of inputs.
+    pub fn show(&mut self, inputs: &Inputs) {
+        unsafe {
+            self.left.set_checked(inputs.a);
+            self.right.set_checked(inputs.b);
+            self.x.set_value(inputs.x.into());
+            self.y.set_value(inputs.y.into());
+        }
+    }
+}
+
+impl<'a> MousePanel<'a> {
+    /// Instantiate a new mouse panel.
+    pub fn new(input_reference: InputHandle<'a>) -> MousePanel<'a> {
+        let mut container = unsafe { QWidget::new_0a() };
+        let mut layout = unsafe { QVBoxLayout::new_1a(&mut container).into_ptr() };
+
+        let (_, x) = spinbox!("X Motion", layout);
+        let (_, y) = spinbox!("Y Motion", layout);
+
+        let mut button_container = unsafe { QWidget::new_0a() };
+        let mut button_layout = unsafe { QHBoxLayout::new_1a(&mut button_container).into_ptr() };
+        let left = checkbox!("Left Button", button_layout);
+        let right = checkbox!("Right Button", button_layout);
+        unsafe { layout.add_widget(button_container.into_ptr()) };
+
+        let left_clicked = unsafe {
+            Slot::new(move || {
+                let checked = left.is_checked();
+                input_reference.edit(None, move |inputs| inputs.a = checked);
+            })
+        };
+        let right_clicked = unsafe {
+            Slot::new(move || {
+                let checked = right.is_checked();
+                input_reference.edit(None, move |inputs| inputs.b = checked);
+            })
+        };
+
+        // holding a spinbox arrow is undone in one step
+        let (x_group, y_group) = (RepeatGroup::default(), RepeatGroup::default());
+        let x_changed = unsafe {
+            SlotOfInt::new(move |val| {
+                let val = val.try_into().unwrap();
+                let group = x_group.group(&input_reference);
+                input_reference.edit(Some(group), move |inputs| inputs.x = val);
+            })
+        };
+        let y_changed = unsafe {
+            SlotOfInt::new(move |val| {
+                let val = val.try_into().unwrap();
+                let group = y_group.group(&input_reference);
+                input_reference.edit(Some(group), move |inputs| inputs.y = val);
+            })
+        };
+
+        let panel = MousePanel {
+            container,
+            inputs: input_reference,
+
+            left,
+            right,
+            x,
+            y,
+
+            left_clicked,
+            right_clicked,
+            x_changed,
+            y_changed,
+        };
+
+        unsafe {
+            left.clicked().connect(&panel.left_clicked);
+            right.clicked().connect(&panel.right_clicked);
+            x.value_changed().connect(&panel.x_changed);
+            y.value_changed().connect(&panel.y_changed);
+        }
+
+        panel
+    }
+
+    /// Get a view for displaying inputs in this panel.
+    pub fn view(&self) -> MouseView {
+        MouseView {
+            left: self.left,
+            right: self.right,
+            x: self.x,
+            y: self.y,
+        }
+    }
+}
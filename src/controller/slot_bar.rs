@@ -0,0 +1,181 @@
+/*
+ * src/controller/slot_bar.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{InputHandle, InputView};
+use crate::{InputSlots, Inputs, SLOT_COUNT};
+use qt_widgets::{
+    cpp_core::{CppBox, MutPtr},
+    qt_core::{QString, Slot},
+    qt_gui::QKeySequence,
+    QHBoxLayout, QPushButton, QShortcut, QWidget,
+};
+use std::{
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+// the text shown on the button for a slot
+fn slot_label(index: usize) -> String {
+    format!("{}", (index + 1) % SLOT_COUNT)
+}
+
+// describe the contents of a slot for its tooltip
+fn slot_tooltip(inputs: Option<Inputs>) -> String {
+    match inputs {
+        Some(i) => format!("Stick: ({}, {})\nValue: {:08X}", i.x, i.y, i.to_value()),
+        None => "Empty".to_string(),
+    }
+}
+
+/// A row of buttons for storing and recalling complete sets of inputs.
+#[allow(dead_code)]
+pub struct SlotBar<'a> {
+    pub container: CppBox<QWidget>,
+    slots_reference: &'a Arc<Mutex<InputSlots>>,
+
+    store_mode: MutPtr<QPushButton>,
+    buttons: Vec<MutPtr<QPushButton>>,
+    recall_shortcuts: Vec<MutPtr<QShortcut>>,
+    store_shortcuts: Vec<MutPtr<QShortcut>>,
+
+    button_clicked: Vec<Slot<'a>>,
+    recall_activated: Vec<Slot<'a>>,
+    store_activated: Vec<Slot<'a>>,
+}
+
+impl<'a> SlotBar<'a> {
+    /// Instantiate a new slot bar. Hotkeys are attached to `window`.
+    pub fn new(
+        input_reference: InputHandle<'a>,
+        slots_reference: &'a Arc<Mutex<InputSlots>>,
+        view: InputView,
+        window: MutPtr<QWidget>,
+    ) -> SlotBar<'a> {
+        let mut container = unsafe { QWidget::new_0a() };
+        let mut layout = unsafe { QHBoxLayout::new_1a(&mut container).into_ptr() };
+
+        // when checked, clicking a slot stores into it instead of recalling it
+        let mut store_mode = unsafe {
+            let mut store_mode = QPushButton::from_q_string(&QString::from_std_str("Store"));
+            store_mode.set_checkable(true);
+            layout.add_widget(&mut store_mode);
+            store_mode.into_ptr()
+        };
+
+        let buttons: Vec<MutPtr<QPushButton>> = (0..SLOT_COUNT)
+            .map(|i| unsafe {
+                let mut button = QPushButton::from_q_string(&QString::from_std_str(slot_label(i)));
+                button.set_tool_tip(&QString::from_std_str(slot_tooltip(
+                    slots_reference.lock().unwrap().get(i),
+                )));
+                layout.add_widget(&mut button);
+                button.into_ptr()
+            })
+            .collect();
+
+        let recall = move |index: usize| {
+            if let Some(inputs) = slots_reference.lock().unwrap().get(index) {
+                input_reference.edit(None, |current| *current = inputs);
+                let mut view = view;
+                view.show(&inputs);
+            }
+        };
+
+        let button_ptrs = buttons.clone();
+        let store = Rc::new(move |index: usize| {
+            let inputs = input_reference.get();
+            let mut slots = slots_reference.lock().unwrap();
+            slots.set(index, Some(inputs));
+            if let Err(e) = slots.save() {
+                dprintln!("Unable to save input slots: {}", e);
+            }
+
+            let mut button = button_ptrs[index];
+            unsafe { button.set_tool_tip(&QString::from_std_str(slot_tooltip(Some(inputs)))) };
+        });
+
+        let button_clicked = (0..SLOT_COUNT)
+            .map(|i| unsafe {
+                let store = store.clone();
+                Slot::new(move || {
+                    if store_mode.is_checked() {
+                        store(i);
+                        store_mode.set_checked(false);
+                    } else {
+                        recall(i);
+                    }
+                })
+            })
+            .collect();
+
+        // ctrl + number recalls a slot, ctrl + shift + number stores into it
+        let shortcut = |sequence: String| unsafe {
+            QShortcut::new_2a(
+                &QKeySequence::from_q_string(&QString::from_std_str(sequence)),
+                window,
+            )
+            .into_ptr()
+        };
+        let recall_shortcuts: Vec<MutPtr<QShortcut>> = (0..SLOT_COUNT)
+            .map(|i| shortcut(format!("Ctrl+{}", slot_label(i))))
+            .collect();
+        let store_shortcuts: Vec<MutPtr<QShortcut>> = (0..SLOT_COUNT)
+            .map(|i| shortcut(format!("Ctrl+Shift+{}", slot_label(i))))
+            .collect();
+
+        let recall_activated = (0..SLOT_COUNT)
+            .map(|i| unsafe { Slot::new(move || recall(i)) })
+            .collect();
+        let store_activated = (0..SLOT_COUNT)
+            .map(|i| unsafe {
+                let store = store.clone();
+                Slot::new(move || store(i))
+            })
+            .collect();
+
+        let sb = SlotBar {
+            container,
+            slots_reference,
+
+            store_mode,
+            buttons,
+            recall_shortcuts,
+            store_shortcuts,
+
+            button_clicked,
+            recall_activated,
+            store_activated,
+        };
+
+        unsafe {
+            for i in 0..SLOT_COUNT {
+                sb.buttons[i].clicked().connect(&sb.button_clicked[i]);
+                sb.recall_shortcuts[i]
+                    .activated()
+                    .connect(&sb.recall_activated[i]);
+                sb.store_shortcuts[i]
+                    .activated()
+                    .connect(&sb.store_activated[i]);
+            }
+        };
+
+        sb
+    }
+}
@@ -0,0 +1,216 @@
+/*
+ * src/core_lib.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Commands sent to the emulator core.
+
+use crate::{config::load_dynamic_lib, state::StateError};
+use std::{
+    convert::TryInto,
+    ffi::{c_void, CString},
+    mem::{self, MaybeUninit},
+    os::raw::c_int,
+    path::Path,
+    ptr,
+};
+
+// the savestate format of mupen64plus itself, rather than that of Project64
+const M64P_STATE_FORMAT: c_int = 1;
+
+/// The signature of `CoreDoCommand` in the core library.
+pub type CoreDoCommand =
+    unsafe extern "C" fn(m64p_sys::m64p_command, c_int, *mut c_void) -> m64p_sys::m64p_error;
+
+/// Identifying information about the currently open ROM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomInfo {
+    pub name: String,
+    pub crc1: u32,
+    pub crc2: u32,
+    pub country: u16,
+}
+
+impl RomInfo {
+    /// A name for the ROM that can be used as part of a file name.
+    pub fn file_stem(&self) -> String {
+        let name: String = self
+            .name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        format!("{}-{:08X}", name, self.crc1)
+    }
+}
+
+/// Functions loaded from the core library.
+#[derive(Clone)]
+pub struct Core {
+    do_command: Option<CoreDoCommand>,
+}
+
+impl Core {
+    /// Load the needed functions from the core library.
+    pub fn new(lib: m64p_sys::m64p_dynlib_handle) -> Core {
+        Core {
+            do_command: unsafe { mem::transmute(load_dynamic_lib(lib, "CoreDoCommand")) },
+        }
+    }
+
+    /// Send a command to the core.
+    ///
+    /// # Safety
+    ///
+    /// `data` must be valid for whatever the core does with it for this command.
+    pub unsafe fn do_command(
+        &self,
+        command: m64p_sys::m64p_command,
+        param: c_int,
+        data: *mut c_void,
+    ) -> Result<(), StateError> {
+        let do_command = self
+            .do_command
+            .ok_or(StateError::StaticMsg("CoreDoCommand is not available"))?;
+
+        match do_command(command, param, data) {
+            m64p_sys::m64p_error_M64ERR_SUCCESS => Ok(()),
+            e => Err(StateError::Core(e)),
+        }
+    }
+
+    /// Get information about the currently open ROM.
+    pub fn rom_info(&self) -> Result<RomInfo, StateError> {
+        let mut header = MaybeUninit::<m64p_sys::m64p_rom_header>::zeroed();
+        let header = unsafe {
+            self.do_command(
+                m64p_sys::m64p_command_M64CMD_ROM_GET_HEADER,
+                mem::size_of::<m64p_sys::m64p_rom_header>()
+                    .try_into()
+                    .unwrap(),
+                header.as_mut_ptr() as *mut c_void,
+            )?;
+            header.assume_init()
+        };
+
+        let name = header
+            .Name
+            .iter()
+            .take_while(|c| **c != 0)
+            .map(|c| *c as char)
+            .collect::<String>()
+            .trim()
+            .to_string();
+
+        // the header is kept in the byte order of the ROM, which is big endian
+        Ok(RomInfo {
+            name,
+            crc1: u32::from_be(header.CRC1),
+            crc2: u32::from_be(header.CRC2),
+            country: header.Country_code,
+        })
+    }
+
+    /// Save a savestate to a file.
+    ///
+    /// The core saves it once the current frame is done, so it may not exist yet on return.
+    pub fn save_state(&self, path: &Path) -> Result<(), StateError> {
+        let path = CString::new(path.to_string_lossy().into_owned())?;
+        unsafe {
+            self.do_command(
+                m64p_sys::m64p_command_M64CMD_STATE_SAVE,
+                M64P_STATE_FORMAT,
+                path.as_ptr() as *mut c_void,
+            )
+        }
+    }
+
+    /// Load a savestate from a file, once the current frame is done.
+    pub fn load_state(&self, path: &Path) -> Result<(), StateError> {
+        let path = CString::new(path.to_string_lossy().into_owned())?;
+        unsafe {
+            self.do_command(
+                m64p_sys::m64p_command_M64CMD_STATE_LOAD,
+                0,
+                path.as_ptr() as *mut c_void,
+            )
+        }
+    }
+
+    /// Pause or resume the emulator, once the current frame is done.
+    pub fn set_paused(&self, paused: bool) -> Result<(), StateError> {
+        let state = if paused {
+            m64p_sys::m64p_emu_state_M64EMU_PAUSED
+        } else {
+            m64p_sys::m64p_emu_state_M64EMU_RUNNING
+        };
+        self.set_core_state(m64p_sys::m64p_core_param_M64CORE_EMU_STATE, state as c_int)
+    }
+
+    /// Whether the emulator is held to the speed of the console.
+    pub fn speed_limiter(&self) -> Result<bool, StateError> {
+        let mut value: c_int = 0;
+        unsafe {
+            self.do_command(
+                m64p_sys::m64p_command_M64CMD_CORE_STATE_QUERY,
+                m64p_sys::m64p_core_param_M64CORE_SPEED_LIMITER as c_int,
+                &mut value as *mut c_int as *mut c_void,
+            )?;
+        }
+        Ok(value != 0)
+    }
+
+    /// Hold the emulator to the speed of the console, or let it run as fast as it can.
+    pub fn set_speed_limiter(&self, enabled: bool) -> Result<(), StateError> {
+        self.set_core_state(
+            m64p_sys::m64p_core_param_M64CORE_SPEED_LIMITER,
+            enabled as c_int,
+        )
+    }
+
+    /// Set the function the core calls after every frame it renders, replacing any other.
+    ///
+    /// The core has no command to read back the function it replaces.
+    pub fn set_frame_callback(
+        &self,
+        callback: m64p_sys::m64p_frame_callback,
+    ) -> Result<(), StateError> {
+        let callback = callback.map_or(ptr::null_mut(), |c| c as *mut c_void);
+        unsafe {
+            self.do_command(
+                m64p_sys::m64p_command_M64CMD_SET_FRAME_CALLBACK,
+                0,
+                callback,
+            )
+        }
+    }
+
+    // change one of the parameters of the core's state
+    fn set_core_state(
+        &self,
+        param: m64p_sys::m64p_core_param,
+        mut value: c_int,
+    ) -> Result<(), StateError> {
+        unsafe {
+            self.do_command(
+                m64p_sys::m64p_command_M64CMD_CORE_STATE_SET,
+                param as c_int,
+                &mut value as *mut c_int as *mut c_void,
+            )
+        }
+    }
+}
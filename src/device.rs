@@ -0,0 +1,75 @@
+/*
+ * src/device.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! The kinds of device that can be plugged into a controller port.
+
+/// A device plugged into a controller port.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Device {
+    /// A standard controller.
+    Controller,
+    /// An N64 Mouse, which reports motion on the stick axes and its buttons as A and B.
+    Mouse,
+}
+
+impl Default for Device {
+    fn default() -> Device {
+        Device::Controller
+    }
+}
+
+impl Device {
+    /// Every kind of device, in the order they are stored in the configuration file.
+    pub const ALL: [Device; 2] = [Device::Controller, Device::Mouse];
+
+    /// A human-readable name for this device.
+    pub fn name(self) -> &'static str {
+        match self {
+            Device::Controller => "Controller",
+            Device::Mouse => "N64 Mouse",
+        }
+    }
+
+    /// The identity reported by this device in response to a status command.
+    pub fn identity(self) -> [u8; 2] {
+        match self {
+            Device::Controller => [0x05, 0x00],
+            Device::Mouse => [0x02, 0x00],
+        }
+    }
+
+    /// Whether this device has a slot for a pak.
+    pub fn has_pak_slot(self) -> bool {
+        self == Device::Controller
+    }
+
+    /// Convert the value stored in the configuration file to a device.
+    pub fn from_int(value: i32) -> Device {
+        Device::ALL
+            .get(value as usize)
+            .copied()
+            .unwrap_or(Device::Controller)
+    }
+
+    /// Convert this device to the value stored in the configuration file.
+    pub fn to_int(self) -> i32 {
+        Device::ALL.iter().position(|d| *d == self).unwrap_or(0) as i32
+    }
+}
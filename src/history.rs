@@ -0,0 +1,306 @@
+/*
+ * src/history.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::{
+    movie::{EventLog, Frame, Markers},
+    Inputs, PatternGenerator,
+};
+use std::collections::VecDeque;
+
+/// The most steps kept in the history before the oldest ones are forgotten.
+pub const HISTORY_LIMIT: usize = 1000;
+
+/// A single change to the inputs of a port, what drives them, or to the movie.
+#[derive(Debug, Clone)]
+pub enum Edit {
+    /// The inputs set in the GUI for a port changed.
+    Inputs {
+        port: usize,
+        before: Inputs,
+        after: Inputs,
+    },
+    /// The stick pattern driving a port changed.
+    Pattern {
+        port: usize,
+        before: Option<PatternGenerator>,
+        after: Option<PatternGenerator>,
+    },
+    /// The frames queued for a port changed.
+    Queue {
+        port: usize,
+        before: VecDeque<Inputs>,
+        after: VecDeque<Inputs>,
+    },
+    /// The frames starting at `start` were replaced, which can insert or remove frames.
+    Frames {
+        start: usize,
+        before: Vec<Frame>,
+        after: Vec<Frame>,
+    },
+    /// The events of the movie changed.
+    Events { before: EventLog, after: EventLog },
+    /// The markers of the movie changed.
+    Markers { before: Markers, after: Markers },
+}
+
+impl Edit {
+    /// The edit that undoes this one.
+    pub fn inverse(&self) -> Edit {
+        match self.clone() {
+            Edit::Inputs {
+                port,
+                before,
+                after,
+            } => Edit::Inputs {
+                port,
+                before: after,
+                after: before,
+            },
+            Edit::Pattern {
+                port,
+                before,
+                after,
+            } => Edit::Pattern {
+                port,
+                before: after,
+                after: before,
+            },
+            Edit::Queue {
+                port,
+                before,
+                after,
+            } => Edit::Queue {
+                port,
+                before: after,
+                after: before,
+            },
+            Edit::Frames {
+                start,
+                before,
+                after,
+            } => Edit::Frames {
+                start,
+                before: after,
+                after: before,
+            },
+            Edit::Events { before, after } => Edit::Events {
+                before: after,
+                after: before,
+            },
+            Edit::Markers { before, after } => Edit::Markers {
+                before: after,
+                after: before,
+            },
+        }
+    }
+
+    /// Tell whether this edit leaves everything as it was.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Edit::Inputs { before, after, .. } => before == after,
+            Edit::Pattern { before, after, .. } => before == after,
+            Edit::Queue { before, after, .. } => before == after,
+            Edit::Frames { before, after, .. } => before == after,
+            Edit::Events { before, after } => before == after,
+            Edit::Markers { before, after } => before == after,
+        }
+    }
+
+    /// The edit turning one list of frames into another, covering only the frames that differ.
+    pub fn frames(before: &[Frame], after: &[Frame]) -> Edit {
+        let prefix = before
+            .iter()
+            .zip(after.iter())
+            .take_while(|(b, a)| b == a)
+            .count();
+        let suffix = before[prefix..]
+            .iter()
+            .rev()
+            .zip(after[prefix..].iter().rev())
+            .take_while(|(b, a)| b == a)
+            .count();
+        Edit::Frames {
+            start: prefix,
+            before: before[prefix..before.len() - suffix].to_vec(),
+            after: after[prefix..after.len() - suffix].to_vec(),
+        }
+    }
+}
+
+// edits undone or redone together
+#[derive(Debug, Clone)]
+struct Step {
+    group: Option<u64>,
+    edits: Vec<Edit>,
+}
+
+/// The edits made to the inputs, so they can be undone and redone.
+///
+/// Edits recorded one after the other with the same group are undone as a single step, so
+/// dragging across several cells or holding a spinbox arrow only takes one undo to revert.
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    next_group: u64,
+    revision: u64,
+}
+
+impl History {
+    /// Create an empty history.
+    pub fn new() -> History {
+        History::default()
+    }
+
+    /// Get a group that no edit has been recorded with yet.
+    pub fn group(&mut self) -> u64 {
+        self.next_group += 1;
+        self.next_group
+    }
+
+    /// A number that changes whenever an edit is recorded, undone or redone.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Record an edit that was just made, forgetting anything that was undone.
+    pub fn record(&mut self, edit: Edit, group: Option<u64>) {
+        if edit.is_empty() {
+            return;
+        }
+        self.revision += 1;
+
+        // nothing was undone since the last edit in the group, so it is still on top
+        let top_group = self.undo.last().and_then(|step| step.group);
+        if group.is_some() && top_group == group && self.redo.is_empty() {
+            self.undo.last_mut().unwrap().edits.push(edit);
+            return;
+        }
+
+        self.redo.clear();
+        self.undo.push(Step {
+            group,
+            edits: vec![edit],
+        });
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Take the last step off the history, returning the edits that revert it in order.
+    pub fn undo(&mut self) -> Option<Vec<Edit>> {
+        let step = self.undo.pop()?;
+        self.revision += 1;
+        let edits = step.edits.iter().rev().map(Edit::inverse).collect();
+
+        // a group is never continued once it has been undone
+        self.redo.push(Step {
+            group: None,
+            edits: step.edits,
+        });
+        Some(edits)
+    }
+
+    /// Put the last undone step back, returning its edits in order.
+    pub fn redo(&mut self) -> Option<Vec<Edit>> {
+        let step = self.redo.pop()?;
+        self.revision += 1;
+        let edits = step.edits.clone();
+        self.undo.push(step);
+        Some(edits)
+    }
+
+    /// Tell whether there is anything to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Tell whether there is anything to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+#[test]
+fn history_test() {
+    let blank = Inputs::from_value(0);
+    let mut pressed = blank;
+    pressed.a = true;
+    let edit = |before: Inputs, after: Inputs| Edit::Inputs {
+        port: 0,
+        before,
+        after,
+    };
+
+    let mut history = History::new();
+    history.record(edit(blank, blank), None);
+    assert!(!history.can_undo());
+
+    // a drag is undone in one step, last edit first
+    let drag = history.group();
+    let mut moved = pressed;
+    moved.x = 20;
+    history.record(edit(blank, pressed), drag.into());
+    history.record(edit(pressed, moved), drag.into());
+    let undone = history.undo().unwrap();
+    assert_eq!(undone.len(), 2);
+    match undone[1] {
+        Edit::Inputs { after, .. } => assert_eq!(after, blank),
+        _ => panic!("expected an input edit"),
+    }
+    assert!(!history.can_undo());
+
+    let redone = history.redo().unwrap();
+    match redone[1] {
+        Edit::Inputs { after, .. } => assert_eq!(after.x, 20),
+        _ => panic!("expected an input edit"),
+    }
+
+    // the group is not reopened after a redo
+    history.undo();
+    history.record(edit(blank, pressed), drag.into());
+    assert!(!history.can_redo());
+    history.record(edit(pressed, moved), drag.into());
+    assert_eq!(history.undo().unwrap().len(), 2);
+}
+
+#[test]
+fn frames_diff_test() {
+    use crate::movie::empty_frame;
+
+    let before = vec![empty_frame(); 4];
+    let mut after = before.clone();
+    after[1][0].a = true;
+    after.insert(2, empty_frame());
+    match Edit::frames(&before, &after) {
+        Edit::Frames {
+            start,
+            before,
+            after,
+        } => {
+            // the new frame is found to be inserted before the unchanged ones
+            assert_eq!(start, 1);
+            assert!(before.is_empty());
+            assert!(after[0][0].a);
+            assert_eq!(after.len(), 1);
+        }
+        _ => panic!("expected a frame edit"),
+    }
+}
@@ -21,7 +21,7 @@
 use std::{convert::TryInto, os::raw::c_int};
 
 /// Directional buttons
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Directional {
     pub up: bool,
     pub down: bool,
@@ -42,7 +42,7 @@ impl Directional {
 }
 
 /// Inputs that can be retrieved from a controller object.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Inputs {
     pub a: bool,
     pub b: bool,
@@ -272,3 +272,97 @@ impl Default for Inputs {
         Inputs::from_value(0)
     }
 }
+
+/// A single button on a controller.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Button {
+    A,
+    B,
+    Z,
+    L,
+    R,
+    Start,
+    CUp,
+    CDown,
+    CLeft,
+    CRight,
+    DUp,
+    DDown,
+    DLeft,
+    DRight,
+}
+
+impl Button {
+    /// Every button, in the order they are shown in the piano roll.
+    pub const ALL: [Button; 14] = [
+        Button::A,
+        Button::B,
+        Button::Z,
+        Button::L,
+        Button::R,
+        Button::Start,
+        Button::CUp,
+        Button::CDown,
+        Button::CLeft,
+        Button::CRight,
+        Button::DUp,
+        Button::DDown,
+        Button::DLeft,
+        Button::DRight,
+    ];
+
+    /// A short name for the button.
+    pub fn name(self) -> &'static str {
+        match self {
+            Button::A => "A",
+            Button::B => "B",
+            Button::Z => "Z",
+            Button::L => "L",
+            Button::R => "R",
+            Button::Start => "S",
+            Button::CUp => "C^",
+            Button::CDown => "Cv",
+            Button::CLeft => "C<",
+            Button::CRight => "C>",
+            Button::DUp => "D^",
+            Button::DDown => "Dv",
+            Button::DLeft => "D<",
+            Button::DRight => "D>",
+        }
+    }
+    /// Find a button by its short name, ignoring case.
+    pub fn from_name(name: &str) -> Option<Button> {
+        Button::ALL
+            .iter()
+            .copied()
+            .find(|b| b.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl Inputs {
+    /// Tell whether a button is pressed.
+    pub fn button(&self, button: Button) -> bool {
+        let mut inputs = *self;
+        *inputs.button_mut(button)
+    }
+
+    /// Get whether a button is pressed, to change it.
+    pub fn button_mut(&mut self, button: Button) -> &mut bool {
+        match button {
+            Button::A => &mut self.a,
+            Button::B => &mut self.b,
+            Button::Z => &mut self.z,
+            Button::L => &mut self.l,
+            Button::R => &mut self.r,
+            Button::Start => &mut self.start,
+            Button::CUp => &mut self.c.up,
+            Button::CDown => &mut self.c.down,
+            Button::CLeft => &mut self.c.left,
+            Button::CRight => &mut self.c.right,
+            Button::DUp => &mut self.d.up,
+            Button::DDown => &mut self.d.down,
+            Button::DLeft => &mut self.d.left,
+            Button::DRight => &mut self.d.right,
+        }
+    }
+}
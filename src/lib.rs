@@ -28,22 +28,47 @@ extern crate lazy_static;
 #[doc(hidden)]
 #[macro_use]
 pub mod debug;
+mod branch_panel;
+mod breakpoint_panel;
+mod breakpoints;
 mod config;
 mod controller;
+mod core_lib;
+mod device;
+mod history;
 mod inputs;
+mod movie;
+mod pak;
+mod piano_roll;
+mod pif;
+mod rules;
+mod slots;
 mod state;
+mod stick;
 
 use std::{
     convert::TryInto,
     ffi::{c_void, CString},
-    os::raw::c_char,
+    os::raw::{c_char, c_uint},
     panic::catch_unwind,
+    slice,
     sync::{atomic::AtomicPtr, Arc, Mutex},
 };
 
+pub use breakpoints::{Breakpoint, Breakpoints};
 pub use controller::*;
-pub use inputs::{Directional, Inputs};
+pub use device::Device;
+pub use history::{Edit, History, HISTORY_LIMIT};
+pub use inputs::{Button, Directional, Inputs};
+pub use movie::{Frame, Movie, MovieHeader, MovieState, PortHeader, RecordMode, StartType};
+pub use pak::{Mpk, MpkError, Note, Pak, NOTE_COUNT};
+pub use pif::{TraceEntry, TraceStage};
+pub use rules::{Rule, RuleAction, Rules};
+pub use slots::{InputSlots, SLOT_COUNT};
 pub use state::Tasinput2State;
+pub use stick::{
+    ClampMode, Interpolation, Keyframe, PatternGenerator, Ramp, StickOrigin, StickPattern,
+};
 
 pub const CONTROLLER_COUNT: usize = 4;
 
@@ -72,7 +97,7 @@ fn get_version_string() -> CString {
 #[allow(non_snake_case)]
 #[no_mangle]
 pub unsafe extern "C" fn PluginStartup(
-    _core_lib_handle: m64p_sys::m64p_dynlib_handle,
+    core_lib_handle: m64p_sys::m64p_dynlib_handle,
     context: *mut c_void,
     debug_callback: unsafe extern "C" fn(*mut c_void, m64p_sys::m64p_msg_level, *const c_char),
 ) -> m64p_sys::m64p_error {
@@ -103,6 +128,8 @@ pub unsafe extern "C" fn PluginStartup(
             return m64p_sys::m64p_error_M64ERR_ALREADY_INIT;
         }
         (*state_lock).is_initialized = true;
+        (*state_lock).core = Some(core_lib::Core::new(core_lib_handle));
+        (*state_lock).config = Some(config::ConfigureFunctions::new(core_lib_handle));
 
         0
     }) {
@@ -210,40 +237,91 @@ pub unsafe extern "C" fn PluginShutdown() -> m64p_sys::m64p_error {
 ///
 /// # Safety
 ///
-/// This function does nothing that is unsafe, because it does nothing.
+/// This function is called exclusively from C code, with a pointer to the command buffer for
+/// the controller. Commands are answered in `ReadController`, so they are only traced here.
 #[allow(non_snake_case)]
 #[no_mangle]
-pub unsafe extern "C" fn ControllerCommand(_controllerNumber: i32, _data_pointer: *mut c_char) {}
+pub unsafe extern "C" fn ControllerCommand(controller_number: i32, data_pointer: *mut c_char) {
+    match catch_unwind(|| {
+        let controller_number: usize = match controller_number.try_into() {
+            Ok(i) if i < CONTROLLER_COUNT => i,
+            _ => return,
+        };
+        if data_pointer.is_null() {
+            return;
+        }
+
+        // the lengths come from the game, so they are kept within the PIF RAM
+        let data = data_pointer as *const u8;
+        let mut state = STATE.lock().unwrap();
+        let len = pif::PifCommand::buffer_len([*data, *data.add(1)])
+            .min(state.max_buffer_len(controller_number, data as usize));
+        let buffer = slice::from_raw_parts(data, len);
+
+        let frame = state.frame();
+        state.trace(frame, pif::TraceStage::Command, controller_number, buffer);
+    }) {
+        Ok(_) => {}
+        Err(e) => dprintln!("Unable to process controller command: {:?}", e),
+    }
+}
 
 /// Read raw data from a controller.
 ///
 /// # Safety
 ///
-/// This function does not do anything.
+/// This function is called exclusively from C code, with a pointer to the command buffer for
+/// the controller.
 #[allow(non_snake_case)]
 #[no_mangle]
-pub unsafe extern "C" fn ReadController(_controller_number: i32, _data_pointer: *mut c_char) {}
+pub unsafe extern "C" fn ReadController(controller_number: i32, data_pointer: *mut c_char) {
+    match catch_unwind(|| {
+        // a negative controller marks the end of the commands for a frame
+        let controller_number: usize = match controller_number.try_into() {
+            Ok(i) if i < CONTROLLER_COUNT => i,
+            _ => return,
+        };
+        if data_pointer.is_null() {
+            return;
+        }
 
-const WORKING_CTRL_COUNT: u8 = 1;
+        // a command too long for what is left of the PIF RAM is cut short, and then rejected
+        let data = data_pointer as *mut u8;
+        let mut state = STATE.lock().unwrap();
+        let len = pif::PifCommand::buffer_len([*data, *data.add(1)])
+            .min(state.max_buffer_len(controller_number, data as usize));
+        let buffer = slice::from_raw_parts_mut(data, len);
+
+        state.raw_command(controller_number, buffer);
+    }) {
+        Ok(_) => {}
+        Err(e) => dprintln!("Unable to read controller: {:?}", e),
+    }
+}
 
 /// Initialize a controller.
 ///
 /// # Safety
 ///
 /// This function is exclusively called from C code.
-///
-/// # TODO
-///
-/// Capture input.
 #[allow(non_snake_case)]
 #[no_mangle]
 pub unsafe extern "C" fn InitiateControllers(controller_info: m64p_sys::CONTROL_INFO) {
     match catch_unwind(|| {
         let mut state = STATE.lock().unwrap();
-        if let Err(e) = (*state).start_qt(WORKING_CTRL_COUNT) {
+        if let Err(e) = (*state).load_settings() {
+            dprintln!("Unable to load settings: {}", e);
+        }
+
+        if !state.settings.raw_data && state.settings.devices.contains(&Device::Mouse) {
+            dprintln!("The N64 Mouse needs RawData to be enabled");
+        }
+
+        state.initiate_controllers(controller_info.Controls);
+
+        if let Err(e) = (*state).start_qt() {
             dprintln!("Error initializing controllers: {}", e);
         }
-        (*controller_info.Controls).Present = 1;
 
         0
     }) {
@@ -263,6 +341,8 @@ pub unsafe extern "C" fn RomOpen() -> i32 {
     match catch_unwind(|| {
         let mut state = STATE.lock().unwrap();
         (*state).is_rom_open = true;
+        state.open_paks();
+        state.open_trace();
 
         0
     }) {
@@ -274,6 +354,13 @@ pub unsafe extern "C" fn RomOpen() -> i32 {
     }
 }
 
+// called by the core after every frame it renders, while the state needs it
+unsafe extern "C" fn frame_callback(_frame_index: c_uint) {
+    if let Err(e) = catch_unwind(|| STATE.lock().unwrap().frame_done()) {
+        dprintln!("Frame callback failed: {:?}", e);
+    }
+}
+
 /// Called when the ROM is closed
 ///
 /// # Safety
@@ -285,6 +372,10 @@ pub unsafe extern "C" fn RomClosed() -> i32 {
     match catch_unwind(|| {
         let mut state = STATE.lock().unwrap();
         (*state).is_rom_open = false;
+        state.close_paks();
+        state.close_trace();
+        // a frame callback the front-end set before ours can't be put back
+        state.set_frame_callback(false);
         if let Err(e) = state.end_qt() {
             dprintln!("Unable to close QT: {:?}", e);
         }
@@ -335,8 +426,11 @@ pub unsafe extern "C" fn GetKeys(controller_num: i32, output: *mut m64p_sys::BUT
             }
         };
 
-        let state = STATE.lock().unwrap();
-        let buttons: Inputs = state.get_inputs(controller_num);
+        let mut state = STATE.lock().unwrap();
+        state.sync_ports(controller_num);
+        // this replaces any frame callback the front-end set, for good
+        state.update_frame_callback();
+        let buttons: Inputs = state.poll_inputs(controller_num);
         let buttons = buttons.to_canonical();
 
         (*output)._bitfield_1 = buttons._bitfield_1;
@@ -0,0 +1,200 @@
+/*
+ * src/movie/branch.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{EventLog, Frame, Movie};
+use std::{
+    ffi::OsStr,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// A named copy of the movie up to some frame, saved along with a savestate of that frame.
+#[derive(Debug, Clone)]
+pub struct Branch {
+    pub name: String,
+    /// The frames and events up to the savestate, with the description in the header.
+    pub movie: Movie,
+}
+
+impl Branch {
+    /// The frame the savestate was made on.
+    pub fn frame(&self) -> usize {
+        self.movie.len()
+    }
+
+    /// What the branch is about.
+    pub fn description(&self) -> &str {
+        &self.movie.header.description
+    }
+
+    /// Replace the frames and events of a movie with the ones of this branch, keeping its header.
+    pub fn restore(&self, movie: &mut Movie) {
+        let len = movie.len();
+        movie.replace(0, len, self.movie.frames().to_vec());
+        movie.events = self.movie.events.clone();
+    }
+}
+
+/// The branches of a ROM, each one kept as a movie and a savestate in a directory.
+#[derive(Debug, Clone, Default)]
+pub struct Branches {
+    branches: Vec<Branch>,
+    directory: Option<PathBuf>,
+}
+
+impl Branches {
+    /// Create an empty set of branches that can't be saved.
+    pub fn new() -> Branches {
+        Branches::default()
+    }
+
+    /// Load every branch in a directory, which is also where new branches are saved.
+    ///
+    /// A directory that does not exist yet results in no branches.
+    pub fn load<P: AsRef<Path>>(directory: P) -> io::Result<Branches> {
+        let directory = directory.as_ref();
+        let mut branches = Branches {
+            branches: Vec::new(),
+            directory: Some(directory.to_path_buf()),
+        };
+
+        let entries = match fs::read_dir(directory) {
+            Ok(e) => e,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(branches),
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension() != Some(OsStr::new("m64")) {
+                continue;
+            }
+            let name = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let movie = Movie::load(&path)?;
+            branches.branches.push(Branch { name, movie });
+        }
+        branches.branches.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(branches)
+    }
+
+    /// Every branch, sorted by name.
+    pub fn list(&self) -> &[Branch] {
+        &self.branches
+    }
+
+    /// Find a branch by name.
+    pub fn get(&self, name: &str) -> Option<&Branch> {
+        self.branches.iter().find(|b| b.name == name)
+    }
+
+    /// The savestate of a branch, which the emulator writes and reads itself.
+    pub fn savestate_path(&self, name: &str) -> Option<PathBuf> {
+        Some(self.directory.as_ref()?.join(format!("{}.st", name)))
+    }
+
+    // the movie of a branch
+    fn movie_path(&self, name: &str) -> Option<PathBuf> {
+        Some(self.directory.as_ref()?.join(format!("{}.m64", name)))
+    }
+
+    /// Save the first `frame` frames of a movie as a branch, replacing any branch of that name.
+    ///
+    /// A movie shorter than that is padded with empty frames. The savestate is not made here,
+    /// see `savestate_path`.
+    pub fn add(
+        &mut self,
+        name: &str,
+        description: &str,
+        movie: &Movie,
+        frame: usize,
+    ) -> io::Result<()> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidInput, message);
+        if name.is_empty() || name.contains(&['/', '\\', '.'][..]) {
+            return Err(invalid(
+                "Branch names can't be empty or contain '/', '\\' or '.'",
+            ));
+        }
+        let path = self
+            .movie_path(name)
+            .ok_or_else(|| invalid("There is no directory to keep branches in"))?;
+
+        let mut movie = movie.clone();
+        let len = movie.len();
+        movie.delete_frames(frame..len);
+        movie.insert_empty(len, frame.saturating_sub(len));
+        movie.header.description = description.to_string();
+
+        fs::create_dir_all(path.parent().unwrap())?;
+        // a branch without events must not pick up the events of the one it replaces
+        let _ = fs::remove_file(EventLog::sidecar_path(&path));
+        movie.save(&path)?;
+
+        self.branches.retain(|b| b.name != name);
+        self.branches.push(Branch {
+            name: name.to_string(),
+            movie,
+        });
+        self.branches.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(())
+    }
+
+    /// Delete a branch along with its savestate.
+    pub fn remove(&mut self, name: &str) -> io::Result<()> {
+        if let (Some(movie), Some(savestate)) = (self.movie_path(name), self.savestate_path(name)) {
+            for path in [EventLog::sidecar_path(&movie), savestate, movie].iter() {
+                match fs::remove_file(path) {
+                    Err(ref e) if e.kind() == io::ErrorKind::NotFound => (),
+                    result => result?,
+                }
+            }
+        }
+        self.branches.retain(|b| b.name != name);
+        Ok(())
+    }
+}
+
+/// The first frame where two runs of frames differ, if they differ at all.
+///
+/// When one is a prefix of the other, they differ at the end of the shorter one.
+pub fn first_difference(a: &[Frame], b: &[Frame]) -> Option<usize> {
+    match a.iter().zip(b.iter()).position(|(a, b)| a != b) {
+        Some(frame) => Some(frame),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+
+#[test]
+fn first_difference_test() {
+    use super::empty_frame;
+
+    let mut changed = empty_frame();
+    changed[1].a = true;
+
+    let a = vec![empty_frame(); 4];
+    assert_eq!(first_difference(&a, &a), None);
+    assert_eq!(first_difference(&a, &a[..3]), Some(3));
+
+    let mut b = a.clone();
+    b[2] = changed;
+    assert_eq!(first_difference(&a, &b), Some(2));
+}
@@ -0,0 +1,214 @@
+/*
+ * src/movie/events.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::pak::Pak;
+use std::{
+    convert::TryInto,
+    fmt, fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+/// Something that happens to the controllers partway through a movie.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MovieEvent {
+    /// A controller was plugged into or unplugged from a port.
+    Plug { port: usize, present: bool },
+    /// The pak in a controller was swapped.
+    Pak { port: usize, pak: Pak },
+    /// A controller took a new stick origin after L, R and Start were held.
+    Recalibrate { port: usize, x: i8, y: i8 },
+}
+
+/// An event and the frame it happens on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TimedEvent {
+    pub frame: u64,
+    pub event: MovieEvent,
+}
+
+impl TimedEvent {
+    /// Parse an event from a line of an event log.
+    pub fn parse(line: &str) -> Option<TimedEvent> {
+        let mut parts = line.split_whitespace();
+        let frame = parts.next()?.parse().ok()?;
+        let kind = parts.next()?;
+        let port = parts.next()?.parse::<usize>().ok()?.checked_sub(1)?;
+        let value: i32 = parts.next()?.parse().ok()?;
+
+        let event = match kind {
+            "recalibrate" => MovieEvent::Recalibrate {
+                port,
+                x: value.try_into().ok()?,
+                y: parts.next()?.parse().ok()?,
+            },
+            "plug" => MovieEvent::Plug {
+                port,
+                present: value != 0,
+            },
+            "pak" => MovieEvent::Pak {
+                port,
+                pak: Pak::from_int(value),
+            },
+            _ => return None,
+        };
+        Some(TimedEvent { frame, event })
+    }
+}
+
+impl fmt::Display for TimedEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.event {
+            MovieEvent::Plug { port, present } => {
+                write!(f, "{} plug {} {}", self.frame, port + 1, present as i32)
+            }
+            MovieEvent::Pak { port, pak } => {
+                write!(f, "{} pak {} {}", self.frame, port + 1, pak.to_int())
+            }
+            MovieEvent::Recalibrate { port, x, y } => {
+                write!(f, "{} recalibrate {} {} {}", self.frame, port + 1, x, y)
+            }
+        }
+    }
+}
+
+/// The events of a movie, kept in a text file next to it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventLog {
+    events: Vec<TimedEvent>,
+}
+
+impl EventLog {
+    /// Create an empty event log.
+    pub fn new() -> EventLog {
+        EventLog::default()
+    }
+
+    /// The file holding the events for a movie.
+    pub fn sidecar_path<P: AsRef<Path>>(movie: P) -> PathBuf {
+        movie.as_ref().with_extension("events")
+    }
+
+    /// Load an event log from a file.
+    ///
+    /// A file that does not exist yet results in an empty log.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<EventLog> {
+        let contents = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(EventLog::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut log = EventLog::new();
+        for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+            let event = TimedEvent::parse(line)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, line.to_string()))?;
+            log.push(event);
+        }
+        Ok(log)
+    }
+
+    /// Write the event log to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let contents: String = self.events.iter().map(|e| format!("{}\n", e)).collect();
+        fs::write(path, contents)
+    }
+
+    /// Add an event, keeping the log in order.
+    pub fn push(&mut self, event: TimedEvent) {
+        let index = self
+            .events
+            .iter()
+            .position(|e| e.frame > event.frame)
+            .unwrap_or(self.events.len());
+        self.events.insert(index, event);
+    }
+
+    /// Every event, in order.
+    pub fn events(&self) -> &[TimedEvent] {
+        &self.events
+    }
+
+    /// The events that happen on a frame.
+    pub fn at(&self, frame: u64) -> impl Iterator<Item = &TimedEvent> {
+        self.events.iter().filter(move |e| e.frame == frame)
+    }
+
+    /// Drop every event from a frame onwards.
+    pub fn truncate(&mut self, frame: u64) {
+        self.events.retain(|e| e.frame < frame);
+    }
+
+    /// Move the events from a frame onwards later, to make room for inserted frames.
+    pub fn insert_frames(&mut self, at: u64, count: u64) {
+        for event in self.events.iter_mut().filter(|e| e.frame >= at) {
+            event.frame += count;
+        }
+    }
+
+    /// Drop the events on a range of deleted frames, moving the ones after it earlier.
+    pub fn delete_frames(&mut self, range: Range<u64>) {
+        let count = range.end.saturating_sub(range.start);
+        self.events.retain(|e| !range.contains(&e.frame));
+        for event in self.events.iter_mut().filter(|e| e.frame >= range.end) {
+            event.frame -= count;
+        }
+    }
+
+    /// Whether the log has no events.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+#[test]
+fn event_log_test() {
+    let mut log = EventLog::new();
+    log.push(TimedEvent {
+        frame: 300,
+        event: MovieEvent::Pak {
+            port: 0,
+            pak: Pak::RumblePak,
+        },
+    });
+    log.push(TimedEvent {
+        frame: 120,
+        event: MovieEvent::Plug {
+            port: 1,
+            present: false,
+        },
+    });
+
+    log.push(TimedEvent {
+        frame: 200,
+        event: MovieEvent::Recalibrate {
+            port: 0,
+            x: -4,
+            y: 12,
+        },
+    });
+
+    let text: String = log.events().iter().map(|e| format!("{}\n", e)).collect();
+    assert_eq!(text, "120 plug 2 0\n200 recalibrate 1 -4 12\n300 pak 1 2\n");
+
+    let parsed: Vec<_> = text.lines().filter_map(TimedEvent::parse).collect();
+    assert_eq!(parsed, log.events());
+    assert_eq!(log.at(120).count(), 1);
+}
@@ -0,0 +1,105 @@
+/*
+ * src/movie/frames.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::MovieHeader;
+use crate::{Inputs, CONTROLLER_COUNT};
+use std::{
+    convert::TryInto,
+    io::{self, Read, Write},
+};
+
+/// The inputs of every port on one frame of a movie.
+pub type Frame = [Inputs; CONTROLLER_COUNT];
+
+/// A frame with nothing pressed on any port.
+pub fn empty_frame() -> Frame {
+    [Inputs::from_value(0); CONTROLLER_COUNT]
+}
+
+/// Read the frames following a movie header.
+///
+/// Each frame holds one sample for every port the header marks as present; the other ports are
+/// left empty.
+pub fn read_frames<R: Read>(header: &MovieHeader, reader: &mut R) -> io::Result<Vec<Frame>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    let count = header.controller_count();
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+
+    // a sample cut off by the end of the file is dropped
+    let frames = bytes
+        .chunks_exact(4 * count)
+        .map(|chunk| {
+            let mut frame = empty_frame();
+            let mut samples = chunk.chunks_exact(4);
+            for (inputs, port) in frame.iter_mut().zip(header.ports.iter()) {
+                if port.present {
+                    let sample = samples.next().unwrap();
+                    *inputs = Inputs::from_value(u32::from_le_bytes(sample.try_into().unwrap()));
+                }
+            }
+            frame
+        })
+        .collect();
+    Ok(frames)
+}
+
+/// Write frames after a movie header, with one sample for every port it marks as present.
+pub fn write_frames<W: Write>(
+    header: &MovieHeader,
+    frames: &[Frame],
+    writer: &mut W,
+) -> io::Result<()> {
+    for frame in frames {
+        for (inputs, port) in frame.iter().zip(header.ports.iter()) {
+            if port.present {
+                writer.write_all(&inputs.to_value().to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn frames_roundtrip_test() {
+    let mut header = MovieHeader::default();
+    header.ports[2].present = true;
+
+    let mut frame = empty_frame();
+    frame[0].a = true;
+    frame[2].x = -40;
+    frame[3].b = true;
+    let frames = vec![frame, empty_frame()];
+
+    let mut bytes = Vec::new();
+    write_frames(&header, &frames, &mut bytes).unwrap();
+    assert_eq!(bytes.len(), 16);
+
+    let read = read_frames(&header, &mut &bytes[..]).unwrap();
+    assert_eq!(read.len(), 2);
+    assert!(read[0][0].a);
+    assert_eq!(read[0][2].x, -40);
+
+    // absent ports are not stored
+    assert!(!read[0][3].b);
+}
@@ -0,0 +1,152 @@
+/*
+ * src/movie/greenzone.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    collections::BTreeSet,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Savestates taken automatically while a movie plays, to seek back to without replaying it all.
+///
+/// The state for a frame is taken before that frame's inputs are sent, so it only depends on
+/// the frames before it.
+#[derive(Debug, Clone, Default)]
+pub struct Greenzone {
+    frames: BTreeSet<u64>,
+    directory: Option<PathBuf>,
+    interval: u64,
+    capacity: usize,
+}
+
+impl Greenzone {
+    /// Create an empty greenzone that never takes any states.
+    pub fn new() -> Greenzone {
+        Greenzone::default()
+    }
+
+    /// Create an empty greenzone kept in a directory, with a state every `interval` frames and
+    /// at most `capacity` states.
+    ///
+    /// States left in the directory from before are deleted, since they were taken from a
+    /// different movie.
+    pub fn open<P: AsRef<Path>>(directory: P, interval: u64, capacity: usize) -> Greenzone {
+        let directory = directory.as_ref();
+        if let Ok(entries) = fs::read_dir(directory) {
+            for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+                if path.extension() == Some(OsStr::new("st")) {
+                    let _ = fs::remove_file(path);
+                }
+            }
+        }
+        let _ = fs::create_dir_all(directory);
+
+        Greenzone {
+            frames: BTreeSet::new(),
+            directory: Some(directory.to_path_buf()),
+            interval,
+            capacity,
+        }
+    }
+
+    /// Tell whether a state should be taken for a frame.
+    pub fn wants(&self, frame: u64) -> bool {
+        self.directory.is_some()
+            && self.interval > 0
+            && self.capacity > 0
+            && frame % self.interval == 0
+            && !self.frames.contains(&frame)
+    }
+
+    /// The file holding the state for a frame.
+    pub fn path(&self, frame: u64) -> Option<PathBuf> {
+        Some(self.directory.as_ref()?.join(format!("{}.st", frame)))
+    }
+
+    /// Record that the state for a frame was taken.
+    ///
+    /// When there are too many states, the ones furthest from this frame are dropped.
+    pub fn insert(&mut self, frame: u64) {
+        self.frames.insert(frame);
+        while self.frames.len() > self.capacity {
+            let first = *self.frames.iter().next().unwrap();
+            let last = *self.frames.iter().next_back().unwrap();
+            let furthest = if frame - first >= last - frame {
+                first
+            } else {
+                last
+            };
+            self.remove(furthest);
+        }
+    }
+
+    /// The latest frame at or before `frame` that has a state.
+    pub fn nearest(&self, frame: u64) -> Option<u64> {
+        self.frames.range(..=frame).next_back().copied()
+    }
+
+    /// Every frame that has a state.
+    pub fn frames(&self) -> impl Iterator<Item = u64> + '_ {
+        self.frames.iter().copied()
+    }
+
+    /// Drop the states after a frame whose inputs changed.
+    pub fn invalidate(&mut self, frame: u64) {
+        let stale: Vec<u64> = self.frames.range(frame + 1..).copied().collect();
+        for frame in stale {
+            self.remove(frame);
+        }
+    }
+
+    // forget a state and delete its file
+    fn remove(&mut self, frame: u64) {
+        self.frames.remove(&frame);
+        if let Some(path) = self.path(frame) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+#[test]
+fn greenzone_test() {
+    let mut greenzone = Greenzone {
+        frames: BTreeSet::new(),
+        directory: Some(PathBuf::from("greenzone-test-does-not-exist")),
+        interval: 10,
+        capacity: 3,
+    };
+    assert!(greenzone.wants(20));
+    assert!(!greenzone.wants(25));
+
+    for frame in [10, 20, 30].iter() {
+        greenzone.insert(*frame);
+    }
+    assert!(!greenzone.wants(20));
+    assert_eq!(greenzone.nearest(29), Some(20));
+    assert_eq!(greenzone.nearest(5), None);
+
+    // the state furthest from the new one goes
+    greenzone.insert(40);
+    assert_eq!(greenzone.frames().collect::<Vec<_>>(), vec![20, 30, 40]);
+
+    greenzone.invalidate(30);
+    assert_eq!(greenzone.frames().collect::<Vec<_>>(), vec![20, 30]);
+}
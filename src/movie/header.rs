@@ -0,0 +1,306 @@
+/*
+ * src/movie/header.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::{pak::Pak, CONTROLLER_COUNT};
+use std::{
+    convert::TryInto,
+    io::{self, Read, Write},
+};
+
+/// The size of a movie header, after which the inputs begin.
+pub const HEADER_SIZE: usize = 0x400;
+
+const SIGNATURE: [u8; 4] = *b"M64\x1A";
+const VERSION: u32 = 3;
+
+// offsets and sizes of the fields in the header
+const UID: usize = 0x008;
+const VI_COUNT: usize = 0x00C;
+const RERECORDS: usize = 0x010;
+const VIS_PER_SECOND: usize = 0x014;
+const CONTROLLER_COUNT_OFFSET: usize = 0x015;
+const SAMPLE_COUNT: usize = 0x018;
+const START_TYPE: usize = 0x01C;
+const CONTROLLER_FLAGS: usize = 0x020;
+const ROM_NAME: (usize, usize) = (0x0C4, 32);
+const ROM_CRC: usize = 0x0E4;
+const ROM_COUNTRY: usize = 0x0E8;
+const VIDEO_PLUGIN: (usize, usize) = (0x122, 64);
+const AUDIO_PLUGIN: (usize, usize) = (0x162, 64);
+const INPUT_PLUGIN: (usize, usize) = (0x1A2, 64);
+const RSP_PLUGIN: (usize, usize) = (0x1E2, 64);
+const AUTHOR: (usize, usize) = (0x222, 222);
+const DESCRIPTION: (usize, usize) = (0x300, 256);
+
+/// How the emulator should be set up before a movie starts.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StartType {
+    /// The movie starts from a savestate stored next to it.
+    Snapshot,
+    /// The movie starts by powering on the console.
+    PowerOn,
+    /// The movie starts by powering on the console with cleared save data.
+    Eeprom,
+}
+
+impl StartType {
+    fn from_int(value: u16) -> Option<StartType> {
+        match value {
+            1 => Some(StartType::Snapshot),
+            2 => Some(StartType::PowerOn),
+            4 => Some(StartType::Eeprom),
+            _ => None,
+        }
+    }
+
+    fn to_int(self) -> u16 {
+        match self {
+            StartType::Snapshot => 1,
+            StartType::PowerOn => 2,
+            StartType::Eeprom => 4,
+        }
+    }
+}
+
+/// What is plugged into a controller port during a movie.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct PortHeader {
+    pub present: bool,
+    pub pak: Pak,
+}
+
+/// The header at the start of a movie.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MovieHeader {
+    pub uid: u32,
+    pub vi_count: u32,
+    pub rerecords: u32,
+    pub vis_per_second: u8,
+    pub sample_count: u32,
+    pub start_type: StartType,
+    pub ports: [PortHeader; CONTROLLER_COUNT],
+    pub rom_name: String,
+    pub rom_crc: u32,
+    pub rom_country: u16,
+    pub video_plugin: String,
+    pub audio_plugin: String,
+    pub input_plugin: String,
+    pub rsp_plugin: String,
+    pub author: String,
+    pub description: String,
+}
+
+// helpers for reading fields out of the header
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_string(bytes: &[u8], (offset, len): (usize, usize)) -> String {
+    let field = &bytes[offset..offset + len];
+    let end = field.iter().position(|b| *b == 0).unwrap_or(len);
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+// helpers for writing fields into the header
+fn write_u16(bytes: &mut [u8], offset: usize, value: u16) {
+    bytes[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_u32(bytes: &mut [u8], offset: usize, value: u32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_string(bytes: &mut [u8], (offset, len): (usize, usize), value: &str) {
+    // truncate on a character boundary, leaving room for the terminator
+    let mut end = value.len().min(len - 1);
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let field = &mut bytes[offset..offset + len];
+    field.iter_mut().for_each(|b| *b = 0);
+    field[..end].copy_from_slice(&value.as_bytes()[..end]);
+}
+
+impl MovieHeader {
+    /// The number of controllers present during the movie.
+    pub fn controller_count(&self) -> usize {
+        self.ports.iter().filter(|p| p.present).count()
+    }
+
+    /// Parse a header from its binary representation.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<MovieHeader> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        if bytes.len() < HEADER_SIZE {
+            return Err(invalid("Movie header is too short"));
+        }
+        if bytes[0..4] != SIGNATURE {
+            return Err(invalid("Movie does not have an M64 signature"));
+        }
+        if read_u32(bytes, 4) != VERSION {
+            return Err(invalid("Unsupported movie version"));
+        }
+
+        // bits 0-3 mark present controllers, 4-7 controller paks and 8-11 rumble paks; bits
+        // 12-15 and 16-19 are used by this plugin to mark transfer paks and raw paks
+        let flags = read_u32(bytes, CONTROLLER_FLAGS);
+        let mut ports = [PortHeader::default(); CONTROLLER_COUNT];
+        for (i, port) in ports.iter_mut().enumerate() {
+            port.present = flags & (1 << i) != 0;
+            port.pak = if flags & (1 << (i + 4)) != 0 {
+                Pak::MemPak
+            } else if flags & (1 << (i + 8)) != 0 {
+                Pak::RumblePak
+            } else if flags & (1 << (i + 12)) != 0 {
+                Pak::TransferPak
+            } else if flags & (1 << (i + 16)) != 0 {
+                Pak::Raw
+            } else {
+                Pak::None
+            };
+        }
+
+        Ok(MovieHeader {
+            uid: read_u32(bytes, UID),
+            vi_count: read_u32(bytes, VI_COUNT),
+            rerecords: read_u32(bytes, RERECORDS),
+            vis_per_second: bytes[VIS_PER_SECOND],
+            sample_count: read_u32(bytes, SAMPLE_COUNT),
+            start_type: StartType::from_int(read_u16(bytes, START_TYPE))
+                .ok_or_else(|| invalid("Unknown movie start type"))?,
+            ports,
+            rom_name: read_string(bytes, ROM_NAME),
+            rom_crc: read_u32(bytes, ROM_CRC),
+            rom_country: read_u16(bytes, ROM_COUNTRY),
+            video_plugin: read_string(bytes, VIDEO_PLUGIN),
+            audio_plugin: read_string(bytes, AUDIO_PLUGIN),
+            input_plugin: read_string(bytes, INPUT_PLUGIN),
+            rsp_plugin: read_string(bytes, RSP_PLUGIN),
+            author: read_string(bytes, AUTHOR),
+            description: read_string(bytes, DESCRIPTION),
+        })
+    }
+
+    /// Convert this header to its binary representation.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0; HEADER_SIZE];
+        bytes[0..4].copy_from_slice(&SIGNATURE);
+        write_u32(&mut bytes, 4, VERSION);
+        write_u32(&mut bytes, UID, self.uid);
+        write_u32(&mut bytes, VI_COUNT, self.vi_count);
+        write_u32(&mut bytes, RERECORDS, self.rerecords);
+        bytes[VIS_PER_SECOND] = self.vis_per_second;
+        bytes[CONTROLLER_COUNT_OFFSET] = self.controller_count() as u8;
+        write_u32(&mut bytes, SAMPLE_COUNT, self.sample_count);
+        write_u16(&mut bytes, START_TYPE, self.start_type.to_int());
+
+        let mut flags = 0;
+        for (i, port) in self.ports.iter().enumerate() {
+            if port.present {
+                flags |= 1 << i;
+            }
+            flags |= match port.pak {
+                Pak::None => 0,
+                Pak::MemPak => 1 << (i + 4),
+                Pak::RumblePak => 1 << (i + 8),
+                Pak::TransferPak => 1 << (i + 12),
+                Pak::Raw => 1 << (i + 16),
+            };
+        }
+        write_u32(&mut bytes, CONTROLLER_FLAGS, flags);
+
+        write_string(&mut bytes, ROM_NAME, &self.rom_name);
+        write_u32(&mut bytes, ROM_CRC, self.rom_crc);
+        write_u16(&mut bytes, ROM_COUNTRY, self.rom_country);
+        write_string(&mut bytes, VIDEO_PLUGIN, &self.video_plugin);
+        write_string(&mut bytes, AUDIO_PLUGIN, &self.audio_plugin);
+        write_string(&mut bytes, INPUT_PLUGIN, &self.input_plugin);
+        write_string(&mut bytes, RSP_PLUGIN, &self.rsp_plugin);
+        write_string(&mut bytes, AUTHOR, &self.author);
+        write_string(&mut bytes, DESCRIPTION, &self.description);
+        bytes
+    }
+
+    /// Read a header from the start of a movie.
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<MovieHeader> {
+        let mut bytes = vec![0; HEADER_SIZE];
+        reader.read_exact(&mut bytes)?;
+        MovieHeader::from_bytes(&bytes)
+    }
+
+    /// Write this header to the start of a movie.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+}
+
+impl Default for MovieHeader {
+    fn default() -> MovieHeader {
+        let mut ports = [PortHeader::default(); CONTROLLER_COUNT];
+        ports[0].present = true;
+
+        MovieHeader {
+            uid: 0,
+            vi_count: 0,
+            rerecords: 0,
+            vis_per_second: 60,
+            sample_count: 0,
+            start_type: StartType::PowerOn,
+            ports,
+            rom_name: String::new(),
+            rom_crc: 0,
+            rom_country: 0,
+            video_plugin: String::new(),
+            audio_plugin: String::new(),
+            input_plugin: String::new(),
+            rsp_plugin: String::new(),
+            author: String::new(),
+            description: String::new(),
+        }
+    }
+}
+
+#[test]
+fn header_roundtrip_test() {
+    let mut header = MovieHeader::default();
+    header.ports[1] = PortHeader {
+        present: true,
+        pak: Pak::RumblePak,
+    };
+    header.ports[0].pak = Pak::MemPak;
+    header.ports[3].pak = Pak::Raw;
+    header.rom_name = "SUPER MARIO 64".to_string();
+    header.author = "not_a_seagull".to_string();
+    header.sample_count = 1234;
+
+    let bytes = header.to_bytes();
+    assert_eq!(bytes.len(), HEADER_SIZE);
+    assert_eq!(bytes[CONTROLLER_COUNT_OFFSET], 2);
+    assert_eq!(
+        read_u32(&bytes, CONTROLLER_FLAGS),
+        0b1000_0000_0010_0001_0011
+    );
+    assert_eq!(MovieHeader::from_bytes(&bytes).unwrap(), header);
+}
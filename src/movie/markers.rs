@@ -0,0 +1,202 @@
+/*
+ * src/movie/markers.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+/// A named landmark on a frame of a movie, with a comment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marker {
+    pub frame: u64,
+    pub name: String,
+    pub comment: String,
+}
+
+// markers are kept one per line, with tabs between the fields
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => (),
+        }
+    }
+    unescaped
+}
+
+/// The markers of a movie, kept in a text file next to it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Markers {
+    markers: Vec<Marker>,
+}
+
+impl Markers {
+    /// Create an empty set of markers.
+    pub fn new() -> Markers {
+        Markers::default()
+    }
+
+    /// The file holding the markers for a movie.
+    pub fn sidecar_path<P: AsRef<Path>>(movie: P) -> PathBuf {
+        movie.as_ref().with_extension("markers")
+    }
+
+    /// Load the markers from a file.
+    ///
+    /// A file that does not exist yet results in no markers.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Markers> {
+        let contents = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Markers::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut markers = Markers::new();
+        for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+            let invalid = || io::Error::new(io::ErrorKind::InvalidData, line.to_string());
+            let mut parts = line.split('\t');
+            let frame = parts
+                .next()
+                .and_then(|f| f.parse().ok())
+                .ok_or_else(invalid)?;
+            let name = parts.next().ok_or_else(invalid)?;
+            markers.set(Marker {
+                frame,
+                name: unescape(name),
+                comment: unescape(parts.next().unwrap_or("")),
+            });
+        }
+        Ok(markers)
+    }
+
+    /// Write the markers to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let contents: String = self
+            .markers
+            .iter()
+            .map(|m| format!("{}\t{}\t{}\n", m.frame, escape(&m.name), escape(&m.comment)))
+            .collect();
+        fs::write(path, contents)
+    }
+
+    /// Every marker, in order.
+    pub fn list(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    /// The marker on a frame.
+    pub fn at(&self, frame: u64) -> Option<&Marker> {
+        self.markers.iter().find(|m| m.frame == frame)
+    }
+
+    /// The last marker on or before a frame.
+    pub fn current(&self, frame: u64) -> Option<&Marker> {
+        self.markers.iter().rev().find(|m| m.frame <= frame)
+    }
+
+    /// The first marker after a frame.
+    pub fn next(&self, frame: u64) -> Option<&Marker> {
+        self.markers.iter().find(|m| m.frame > frame)
+    }
+
+    /// The last marker before a frame.
+    pub fn previous(&self, frame: u64) -> Option<&Marker> {
+        self.markers.iter().rev().find(|m| m.frame < frame)
+    }
+
+    /// Put a marker on its frame, replacing the one already there.
+    pub fn set(&mut self, marker: Marker) {
+        self.remove(marker.frame);
+        let index = self
+            .markers
+            .iter()
+            .position(|m| m.frame > marker.frame)
+            .unwrap_or(self.markers.len());
+        self.markers.insert(index, marker);
+    }
+
+    /// Remove the marker on a frame.
+    pub fn remove(&mut self, frame: u64) {
+        self.markers.retain(|m| m.frame != frame);
+    }
+
+    /// Move the markers from a frame onwards later, to make room for inserted frames.
+    pub fn insert_frames(&mut self, at: u64, count: u64) {
+        for marker in self.markers.iter_mut().filter(|m| m.frame >= at) {
+            marker.frame += count;
+        }
+    }
+
+    /// Drop the markers on a range of deleted frames, moving the ones after it earlier.
+    pub fn delete_frames(&mut self, range: Range<u64>) {
+        let count = range.end.saturating_sub(range.start);
+        self.markers.retain(|m| !range.contains(&m.frame));
+        for marker in self.markers.iter_mut().filter(|m| m.frame >= range.end) {
+            marker.frame -= count;
+        }
+    }
+
+    /// Whether there are no markers.
+    pub fn is_empty(&self) -> bool {
+        self.markers.is_empty()
+    }
+}
+
+#[test]
+fn markers_test() {
+    let marker = |frame: u64, name: &str| Marker {
+        frame,
+        name: name.to_string(),
+        comment: String::new(),
+    };
+    let mut markers = Markers::new();
+    markers.set(marker(300, "castle"));
+    markers.set(marker(100, "start"));
+    markers.set(marker(100, "intro"));
+    assert_eq!(markers.list().len(), 2);
+    assert_eq!(markers.at(100).unwrap().name, "intro");
+    assert_eq!(markers.current(299).unwrap().name, "intro");
+    assert_eq!(markers.next(100).unwrap().name, "castle");
+    assert_eq!(markers.previous(100), None);
+
+    markers.insert_frames(200, 10);
+    assert_eq!(markers.next(100).unwrap().frame, 310);
+    markers.delete_frames(50..150);
+    assert_eq!(markers.list(), &[marker(210, "castle")][..]);
+
+    let text = "a\tb\\c\nd";
+    assert_eq!(unescape(&escape(text)), text);
+}
@@ -0,0 +1,315 @@
+/*
+ * src/movie/mod.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Movies of recorded inputs, stored in the `.m64` format used by mupen64-rr.
+
+mod branch;
+mod events;
+mod frames;
+mod greenzone;
+mod header;
+mod markers;
+mod record;
+
+pub use branch::{first_difference, Branch, Branches};
+pub use events::{EventLog, MovieEvent, TimedEvent};
+pub use frames::{empty_frame, read_frames, write_frames, Frame};
+pub use greenzone::Greenzone;
+pub use header::{MovieHeader, PortHeader, StartType, HEADER_SIZE};
+pub use markers::{Marker, Markers};
+pub use record::{MovieState, RecordMode, Seek};
+
+use crate::{Button, Inputs, Ramp, CONTROLLER_COUNT};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    ops::Range,
+    path::Path,
+};
+
+/// A movie being edited: its header, the inputs of every frame, its events and its markers.
+///
+/// Ranges of frames past the end of the movie are clamped when reading or removing frames, and
+/// filled in with empty frames when setting inputs. Ranges that end before they start are empty,
+/// and inputs of ports past the last one are left alone.
+#[derive(Debug, Clone, Default)]
+pub struct Movie {
+    pub header: MovieHeader,
+    frames: Vec<Frame>,
+    pub events: EventLog,
+    pub markers: Markers,
+}
+
+impl Movie {
+    /// Create a movie with no frames.
+    pub fn new(header: MovieHeader) -> Movie {
+        Movie {
+            header,
+            frames: Vec::new(),
+            events: EventLog::new(),
+            markers: Markers::new(),
+        }
+    }
+
+    /// Load a movie, along with the events and markers next to it if there are any.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Movie> {
+        let mut reader = BufReader::new(File::open(path.as_ref())?);
+        let header = MovieHeader::read(&mut reader)?;
+        let frames = read_frames(&header, &mut reader)?;
+        let events = EventLog::load(EventLog::sidecar_path(&path))?;
+        let markers = Markers::load(Markers::sidecar_path(path))?;
+        Ok(Movie {
+            header,
+            frames,
+            events,
+            markers,
+        })
+    }
+
+    /// Save the movie, keeping the sample count in its header up to date.
+    ///
+    /// The events and markers are written next to it, if there are any.
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.header.sample_count = (self.frames.len() * self.header.controller_count()) as u32;
+        let mut writer = BufWriter::new(File::create(path.as_ref())?);
+        self.header.write(&mut writer)?;
+        write_frames(&self.header, &self.frames, &mut writer)?;
+
+        if !self.events.is_empty() {
+            self.events.save(EventLog::sidecar_path(&path))?;
+        }
+        if !self.markers.is_empty() {
+            self.markers.save(Markers::sidecar_path(path))?;
+        }
+        Ok(())
+    }
+
+    /// The number of frames in the movie.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Whether the movie has no frames.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Every frame in the movie.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// A single frame, if the movie is long enough.
+    pub fn frame(&self, index: usize) -> Option<&Frame> {
+        self.frames.get(index)
+    }
+
+    /// Replace `count` frames starting at `start`, returning the frames that were replaced.
+    ///
+    /// Events and markers are left where they are.
+    pub fn replace(&mut self, start: usize, count: usize, frames: Vec<Frame>) -> Vec<Frame> {
+        let range = self.clamp(start..start + count);
+        self.frames.splice(range, frames).collect()
+    }
+
+    /// Insert frames before a frame, moving everything after them later.
+    pub fn insert_frames(&mut self, at: usize, frames: Vec<Frame>) {
+        let at = at.min(self.frames.len());
+        self.events.insert_frames(at as u64, frames.len() as u64);
+        self.markers.insert_frames(at as u64, frames.len() as u64);
+        self.frames.splice(at..at, frames);
+    }
+
+    /// Insert empty frames before a frame, moving everything after them later.
+    pub fn insert_empty(&mut self, at: usize, count: usize) {
+        self.insert_frames(at, vec![empty_frame(); count]);
+    }
+
+    /// Delete a range of frames, moving everything after them earlier.
+    pub fn delete_frames(&mut self, range: Range<usize>) -> Vec<Frame> {
+        let range = self.clamp(range);
+        self.events
+            .delete_frames(range.start as u64..range.end as u64);
+        self.markers
+            .delete_frames(range.start as u64..range.end as u64);
+        self.frames.drain(range).collect()
+    }
+
+    /// Press or release a button of a port over a range of frames.
+    pub fn set_button(&mut self, port: usize, button: Button, range: Range<usize>, pressed: bool) {
+        if port >= CONTROLLER_COUNT {
+            return;
+        }
+        self.extend_to(range.end);
+        let range = self.clamp(range);
+        for frame in self.frames[range].iter_mut() {
+            *frame[port].button_mut(button) = pressed;
+        }
+    }
+
+    /// Hold the stick of a port at a position over a range of frames.
+    pub fn set_stick(&mut self, port: usize, range: Range<usize>, x: i8, y: i8) {
+        if port >= CONTROLLER_COUNT {
+            return;
+        }
+        self.extend_to(range.end);
+        let range = self.clamp(range);
+        for frame in self.frames[range].iter_mut() {
+            frame[port].x = x;
+            frame[port].y = y;
+        }
+    }
+
+    /// Move the stick of a port along a ramp, over the frames between its keyframes.
+    pub fn set_ramp(&mut self, port: usize, ramp: &Ramp) {
+        if port >= CONTROLLER_COUNT {
+            return;
+        }
+        let start = ramp.start().frame as usize;
+        let end = start + ramp.frame_count();
+        self.extend_to(end);
+        ramp.apply(
+            self.frames[start..end]
+                .iter_mut()
+                .map(|frame| &mut frame[port]),
+        );
+    }
+
+    /// Copy the inputs of a port over a range of frames.
+    pub fn copy(&self, port: usize, range: Range<usize>) -> Vec<Inputs> {
+        if port >= CONTROLLER_COUNT {
+            return Vec::new();
+        }
+        self.frames[self.clamp(range)]
+            .iter()
+            .map(|frame| frame[port])
+            .collect()
+    }
+
+    /// Overwrite the inputs of a port starting at a frame, which can be on a different port
+    /// than they were copied from.
+    pub fn paste(&mut self, port: usize, at: usize, inputs: &[Inputs]) {
+        if port >= CONTROLLER_COUNT {
+            return;
+        }
+        self.extend_to(at + inputs.len());
+        for (frame, inputs) in self.frames[at..].iter_mut().zip(inputs.iter()) {
+            frame[port] = *inputs;
+        }
+    }
+
+    /// Move the inputs of a port from a frame onwards by a number of frames, leaving the other
+    /// ports in place.
+    ///
+    /// Moving them later leaves empty inputs behind, and moving them earlier overwrites the
+    /// inputs before them.
+    pub fn shift(&mut self, port: usize, from: usize, offset: isize) {
+        if from >= self.frames.len() {
+            return;
+        }
+        let inputs = self.copy(port, from..self.frames.len());
+        let blank = vec![Inputs::default(); inputs.len()];
+        self.paste(port, from, &blank);
+
+        let to = (from as isize + offset).max(0) as usize;
+        let skipped = (to as isize - (from as isize + offset)) as usize;
+        self.paste(port, to, &inputs[skipped.min(inputs.len())..]);
+    }
+
+    // limit a range to the frames in the movie
+    fn clamp(&self, range: Range<usize>) -> Range<usize> {
+        let end = range.end.min(self.frames.len());
+        range.start.min(end)..end
+    }
+
+    // add empty frames until the movie is at least `len` long
+    fn extend_to(&mut self, len: usize) {
+        if self.frames.len() < len {
+            self.frames.resize(len, empty_frame());
+        }
+    }
+}
+
+#[test]
+fn movie_edit_test() {
+    use crate::{ClampMode, Interpolation, Keyframe};
+    use events::MovieEvent;
+
+    let mut movie = Movie::default();
+    movie.set_button(0, Button::A, 2..4, true);
+    assert_eq!(movie.len(), 4);
+    assert!(!movie.frames()[1][0].a && movie.frames()[3][0].a);
+
+    movie.set_stick(1, 0..2, 10, -10);
+    movie.events.push(TimedEvent {
+        frame: 3,
+        event: MovieEvent::Plug {
+            port: 1,
+            present: false,
+        },
+    });
+
+    // copy port 1 onto port 2 a frame later
+    let copied = movie.copy(0, 0..10);
+    assert_eq!(copied.len(), 4);
+    movie.paste(2, 1, &copied);
+    assert_eq!(movie.len(), 5);
+    assert!(movie.frames()[4][2].a);
+
+    movie.insert_empty(1, 2);
+    assert_eq!(movie.len(), 7);
+    assert_eq!(movie.frames()[0][1].x, 10);
+    assert_eq!(movie.frames()[3][1].y, -10);
+    assert_eq!(movie.events.events()[0].frame, 5);
+
+    let deleted = movie.delete_frames(1..3);
+    assert_eq!(deleted.len(), 2);
+    assert_eq!(movie.events.events()[0].frame, 3);
+
+    // shifting one port leaves the others alone
+    movie.shift(0, 2, -1);
+    assert!(movie.frames()[1][0].a && movie.frames()[2][0].a && !movie.frames()[3][0].a);
+    assert!(movie.frames()[3][2].a);
+    movie.shift(0, 0, 2);
+    assert!(!movie.frames()[1][0].a && movie.frames()[3][0].a);
+
+    // a ramp only moves the stick of its own port
+    let ramp = Ramp::new(
+        Keyframe::new(1, 0, 0),
+        Keyframe::new(3, 100, -50),
+        Interpolation::Linear,
+        ClampMode::Raw,
+    );
+    movie.set_ramp(1, &ramp);
+    let sticks: Vec<(i8, i8)> = movie.frames()[1..4]
+        .iter()
+        .map(|f| (f[1].x, f[1].y))
+        .collect();
+    assert_eq!(sticks, vec![(0, 0), (50, -25), (100, -50)]);
+    assert!(movie.frames()[3][0].a);
+
+    // reversed ranges and missing ports change nothing
+    let frames = movie.frames().to_vec();
+    movie.set_button(0, Button::B, Range { start: 4, end: 2 }, true);
+    movie.set_stick(CONTROLLER_COUNT, 0..2, 5, 5);
+    movie.paste(CONTROLLER_COUNT, 0, &copied);
+    assert_eq!(movie.frames(), &frames[..]);
+    assert!(movie.copy(CONTROLLER_COUNT, 0..2).is_empty());
+}
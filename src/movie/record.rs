@@ -0,0 +1,145 @@
+/*
+ * src/movie/record.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::Inputs;
+
+// the bits of `Inputs::to_value` holding the buttons, below the stick bytes
+const BUTTON_MASK: u32 = 0xffff;
+
+/// What is done with the movie as frames are sent to the emulator.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MovieState {
+    /// The movie is left alone and the inputs come from the GUI.
+    Stopped,
+    /// The inputs come from the movie, until it runs out of frames.
+    Playing,
+    /// The inputs from the GUI are written into the movie, undone as the given history group.
+    Recording(u64),
+}
+
+impl Default for MovieState {
+    fn default() -> MovieState {
+        MovieState::Stopped
+    }
+}
+
+/// A movie being played to a frame, to pause on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Seek {
+    /// The next frame to be sent when the emulator pauses.
+    pub target: u64,
+    /// What is done with the movie once the target is reached.
+    pub resume: MovieState,
+    /// Whether the speed limiter was on before seeking, to turn it back on.
+    pub speed_limiter: bool,
+}
+
+/// How the inputs from the GUI are written into a movie that is being recorded over.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RecordMode {
+    /// The frame in the movie is replaced.
+    Overwrite,
+    /// A new frame is inserted, moving the rest of the movie one frame later.
+    Insert,
+    /// The buttons pressed in the GUI are toggled on the frame in the movie.
+    Xor,
+}
+
+impl Default for RecordMode {
+    fn default() -> RecordMode {
+        RecordMode::Overwrite
+    }
+}
+
+impl RecordMode {
+    /// Every mode, in the order of their configuration values.
+    pub const ALL: [RecordMode; 3] = [RecordMode::Overwrite, RecordMode::Insert, RecordMode::Xor];
+
+    /// The name shown to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            RecordMode::Overwrite => "Overwrite",
+            RecordMode::Insert => "Insert",
+            RecordMode::Xor => "XOR",
+        }
+    }
+
+    /// Convert the value stored in the configuration file to a mode.
+    pub fn from_int(value: i32) -> RecordMode {
+        match value {
+            1 => RecordMode::Insert,
+            2 => RecordMode::Xor,
+            _ => RecordMode::Overwrite,
+        }
+    }
+
+    /// Convert this mode to the value stored in the configuration file.
+    pub fn to_int(self) -> i32 {
+        match self {
+            RecordMode::Overwrite => 0,
+            RecordMode::Insert => 1,
+            RecordMode::Xor => 2,
+        }
+    }
+
+    /// The inputs to store over the recorded ones, given the inputs from the GUI.
+    ///
+    /// When XORing, the recorded stick is kept unless the stick in the GUI is off center.
+    pub fn combine(self, recorded: Inputs, live: Inputs) -> Inputs {
+        match self {
+            RecordMode::Overwrite | RecordMode::Insert => live,
+            RecordMode::Xor => {
+                let buttons = (recorded.to_value() ^ live.to_value()) & BUTTON_MASK;
+                let mut inputs = Inputs::from_value(buttons);
+                let stick = if live.x != 0 || live.y != 0 {
+                    live
+                } else {
+                    recorded
+                };
+                inputs.x = stick.x;
+                inputs.y = stick.y;
+                inputs
+            }
+        }
+    }
+}
+
+#[test]
+fn combine_test() {
+    let mut recorded = Inputs::from_value(0);
+    recorded.a = true;
+    recorded.b = true;
+    recorded.x = 40;
+    let mut live = Inputs::from_value(0);
+    live.a = true;
+    live.z = true;
+
+    assert_eq!(RecordMode::Overwrite.combine(recorded, live), live);
+
+    let xored = RecordMode::Xor.combine(recorded, live);
+    assert!(!xored.a);
+    assert!(xored.b);
+    assert!(xored.z);
+    assert_eq!((xored.x, xored.y), (40, 0));
+
+    live.y = -20;
+    let xored = RecordMode::Xor.combine(recorded, live);
+    assert_eq!((xored.x, xored.y), (0, -20));
+}
@@ -0,0 +1,233 @@
+/*
+ * src/pak/gb_cart.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// where the cartridge type and RAM size are stored in the ROM header
+const CART_TYPE: usize = 0x147;
+const RAM_SIZE: usize = 0x149;
+
+const ROM_BANK_SIZE: usize = 0x4000;
+const RAM_BANK_SIZE: usize = 0x2000;
+
+/// The memory bank controller of a Game Boy cartridge.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mbc {
+    None,
+    Mbc1,
+    Mbc3,
+    Mbc5,
+}
+
+impl Mbc {
+    // the controller used by a cartridge type, or `None` if it isn't supported
+    fn from_cart_type(cart_type: u8) -> Option<Mbc> {
+        match cart_type {
+            0x00 | 0x08 | 0x09 => Some(Mbc::None),
+            0x01..=0x03 => Some(Mbc::Mbc1),
+            0x0F..=0x13 => Some(Mbc::Mbc3),
+            0x19..=0x1E => Some(Mbc::Mbc5),
+            _ => None,
+        }
+    }
+}
+
+/// A Game Boy cartridge, made of a ROM and the battery backed RAM holding its saves.
+pub struct GbCart {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    mbc: Mbc,
+    ram_enabled: bool,
+    rom_bank: usize,
+    // the upper ROM bank bits on an MBC1, or the RAM bank on others
+    upper_bank: usize,
+    // whether the upper bits select the RAM bank on an MBC1
+    ram_banking: bool,
+    save_path: Option<PathBuf>,
+    dirty: bool,
+}
+
+impl GbCart {
+    /// Create a cartridge from the contents of its ROM, with empty RAM.
+    pub fn new(rom: Vec<u8>) -> io::Result<GbCart> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        if rom.len() < ROM_BANK_SIZE * 2 {
+            return Err(invalid("Game Boy ROM is too small"));
+        }
+        let mbc = Mbc::from_cart_type(rom[CART_TYPE])
+            .ok_or_else(|| invalid("Unsupported Game Boy cartridge type"))?;
+        let ram_size = match rom[RAM_SIZE] {
+            1 => 0x800,
+            2 => 0x2000,
+            3 => 0x8000,
+            4 => 0x20000,
+            5 => 0x10000,
+            _ => 0,
+        };
+
+        Ok(GbCart {
+            rom,
+            ram: vec![0; ram_size],
+            mbc,
+            ram_enabled: false,
+            rom_bank: 1,
+            upper_bank: 0,
+            ram_banking: false,
+            save_path: None,
+            dirty: false,
+        })
+    }
+
+    /// Load a cartridge from a ROM file and its save file, which is also used for saving it.
+    ///
+    /// A save file that does not exist yet results in empty RAM.
+    pub fn load<P: AsRef<Path>, Q: AsRef<Path>>(rom_path: P, save_path: Q) -> io::Result<GbCart> {
+        let mut cart = GbCart::new(fs::read(rom_path)?)?;
+        let save_path = save_path.as_ref();
+
+        match fs::read(save_path) {
+            Ok(save) => {
+                let len = save.len().min(cart.ram.len());
+                cart.ram[..len].copy_from_slice(&save[..len]);
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        cart.save_path = Some(save_path.to_path_buf());
+
+        Ok(cart)
+    }
+
+    /// Write the RAM to the save file it was loaded from, if any.
+    pub fn save(&mut self) -> io::Result<()> {
+        let path = match self.save_path {
+            Some(ref p) if self.dirty && !self.ram.is_empty() => p,
+            _ => return Ok(()),
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, &self.ram)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// The memory bank controller of this cartridge.
+    pub fn mbc(&self) -> Mbc {
+        self.mbc
+    }
+
+    // the ROM bank mapped at 0x0000 and the one mapped at 0x4000
+    fn rom_banks(&self) -> (usize, usize) {
+        match self.mbc {
+            Mbc::None => (0, 1),
+            Mbc::Mbc1 if self.ram_banking => {
+                (self.upper_bank << 5, (self.upper_bank << 5) | self.rom_bank)
+            }
+            Mbc::Mbc1 => (0, (self.upper_bank << 5) | self.rom_bank),
+            Mbc::Mbc3 | Mbc::Mbc5 => (0, self.rom_bank),
+        }
+    }
+
+    // the offset into the RAM for an address in 0xA000..0xC000
+    fn ram_offset(&self, address: u16) -> Option<usize> {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return None;
+        }
+
+        let bank = match self.mbc {
+            Mbc::Mbc1 if !self.ram_banking => 0,
+            Mbc::None => 0,
+            _ => self.upper_bank,
+        };
+        let offset = bank * RAM_BANK_SIZE + (address as usize - 0xA000);
+        Some(offset % self.ram.len())
+    }
+
+    /// Read a byte from the cartridge's address space.
+    pub fn read(&self, address: u16) -> u8 {
+        let (low_bank, high_bank) = self.rom_banks();
+        match address {
+            0x0000..=0x3FFF => self.rom_byte(low_bank, address as usize),
+            0x4000..=0x7FFF => self.rom_byte(high_bank, address as usize - ROM_BANK_SIZE),
+            0xA000..=0xBFFF => self.ram_offset(address).map_or(0xFF, |o| self.ram[o]),
+            _ => 0xFF,
+        }
+    }
+
+    fn rom_byte(&self, bank: usize, offset: usize) -> u8 {
+        let bank_count = self.rom.len() / ROM_BANK_SIZE;
+        self.rom[(bank % bank_count) * ROM_BANK_SIZE + offset]
+    }
+
+    /// Write a byte to the cartridge's address space, switching banks or writing to RAM.
+    pub fn write(&mut self, address: u16, value: u8) {
+        let value = value as usize;
+        match (self.mbc, address) {
+            (_, 0x0000..=0x1FFF) => self.ram_enabled = value & 0x0F == 0x0A,
+            (Mbc::Mbc1, 0x2000..=0x3FFF) => self.rom_bank = (value & 0x1F).max(1),
+            (Mbc::Mbc3, 0x2000..=0x3FFF) => self.rom_bank = (value & 0x7F).max(1),
+            (Mbc::Mbc5, 0x2000..=0x2FFF) => self.rom_bank = (self.rom_bank & 0x100) | value,
+            (Mbc::Mbc5, 0x3000..=0x3FFF) => {
+                self.rom_bank = (self.rom_bank & 0xFF) | ((value & 0x01) << 8)
+            }
+            (Mbc::Mbc1, 0x4000..=0x5FFF) => self.upper_bank = value & 0x03,
+            // the real time clock registers are not emulated
+            (Mbc::Mbc3, 0x4000..=0x5FFF) => self.upper_bank = value & 0x03,
+            (Mbc::Mbc5, 0x4000..=0x5FFF) => self.upper_bank = value & 0x0F,
+            (Mbc::Mbc1, 0x6000..=0x7FFF) => self.ram_banking = value & 0x01 != 0,
+            (_, 0xA000..=0xBFFF) => {
+                if let Some(offset) = self.ram_offset(address) {
+                    self.ram[offset] = value as u8;
+                    self.dirty = true;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn mbc1_banking_test() {
+    let mut rom = vec![0; ROM_BANK_SIZE * 8];
+    for (bank, chunk) in rom.chunks_mut(ROM_BANK_SIZE).enumerate() {
+        chunk[0x100] = bank as u8;
+    }
+    rom[CART_TYPE] = 0x03;
+    rom[RAM_SIZE] = 0x02;
+    let mut cart = GbCart::new(rom).unwrap();
+
+    assert_eq!(cart.read(0x4100), 1);
+    cart.write(0x2000, 5);
+    assert_eq!(cart.read(0x4100), 5);
+    // bank zero is mapped to bank one in the switchable area
+    cart.write(0x2000, 0);
+    assert_eq!(cart.read(0x4100), 1);
+
+    assert_eq!(cart.read(0xA000), 0xFF);
+    cart.write(0x0000, 0x0A);
+    cart.write(0xA000, 0x42);
+    assert_eq!(cart.read(0xA000), 0x42);
+}
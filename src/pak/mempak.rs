@@ -0,0 +1,181 @@
+/*
+ * src/pak/mempak.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{PakDevice, BLOCK_SIZE};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The size of the memory in a Controller Pak.
+pub const MEMPAK_SIZE: usize = 0x8000;
+/// The size of a page, the unit in which space on a Controller Pak is allocated.
+pub const PAGE_SIZE: usize = 0x100;
+
+// where the ID block and its backups are kept in the first page
+const ID_BLOCKS: [usize; 4] = [0x20, 0x60, 0x80, 0xC0];
+// the first page available for saves
+const FIRST_DATA_PAGE: usize = 5;
+
+/// A Controller Pak, optionally backed by a `.mpk` file.
+pub struct MemPak {
+    data: Box<[u8]>,
+    path: Option<PathBuf>,
+    dirty: bool,
+}
+
+impl MemPak {
+    /// Create a freshly formatted Controller Pak that is only kept in memory.
+    pub fn new() -> MemPak {
+        let mut data = vec![0; MEMPAK_SIZE].into_boxed_slice();
+        format(&mut data);
+        MemPak {
+            data,
+            path: None,
+            dirty: false,
+        }
+    }
+
+    /// Load a Controller Pak from a file, which is also used for saving it later on.
+    ///
+    /// A file that does not exist yet results in a freshly formatted pak.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<MemPak> {
+        let path = path.as_ref();
+        let mut pak = MemPak::new();
+        pak.path = Some(path.to_path_buf());
+
+        match fs::read(path) {
+            Ok(contents) => {
+                if contents.len() < MEMPAK_SIZE {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{} is not a Controller Pak image", path.display()),
+                    ));
+                }
+                pak.data.copy_from_slice(&contents[..MEMPAK_SIZE]);
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => pak.dirty = true,
+            Err(e) => return Err(e),
+        }
+
+        Ok(pak)
+    }
+
+    /// Write the pak to the file it was loaded from, if any.
+    pub fn save(&mut self) -> io::Result<()> {
+        let path = match self.path {
+            Some(ref p) if self.dirty => p,
+            _ => return Ok(()),
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, &self.data)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// The raw contents of the pak.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Default for MemPak {
+    fn default() -> MemPak {
+        Self::new()
+    }
+}
+
+impl PakDevice for MemPak {
+    fn read(&mut self, address: u16, data: &mut [u8; BLOCK_SIZE]) {
+        let address = address as usize;
+        match self.data.get(address..address + BLOCK_SIZE) {
+            Some(block) => data.copy_from_slice(block),
+            // nothing lives past the end of the memory
+            None => data.iter_mut().for_each(|b| *b = 0),
+        }
+    }
+
+    fn write(&mut self, address: u16, data: &[u8; BLOCK_SIZE]) {
+        let address = address as usize;
+        if let Some(block) = self.data.get_mut(address..address + BLOCK_SIZE) {
+            block.copy_from_slice(data);
+            self.dirty = true;
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.save()
+    }
+}
+
+/// The checksum of an ID block and its inverse, stored in its last four bytes.
+pub fn id_checksum(block: &[u8]) -> (u16, u16) {
+    let sum = block[..0x1C].chunks(2).fold(0u16, |sum, w| {
+        sum.wrapping_add(u16::from_be_bytes([w[0], w[1]]))
+    });
+    (sum, 0xFFF2u16.wrapping_sub(sum))
+}
+
+/// Erase a Controller Pak, leaving it empty but valid.
+pub fn format(data: &mut [u8]) {
+    data.iter_mut().for_each(|b| *b = 0);
+
+    // the label area, which games do not check
+    for (i, b) in data[..0x20].iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    data[0] = 0x81;
+
+    // the ID block: serial number, device ID and bank count, then the checksum
+    let mut id = [0u8; 0x20];
+    id[..8].copy_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF, 0x05, 0x1A, 0x5F, 0x13]);
+    id[0x10..0x1C].copy_from_slice(&[
+        0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0xFF,
+    ]);
+    let (sum, inverse) = id_checksum(&id);
+    id[0x1C..0x1E].copy_from_slice(&sum.to_be_bytes());
+    id[0x1E..].copy_from_slice(&inverse.to_be_bytes());
+    for offset in ID_BLOCKS.iter() {
+        data[*offset..*offset + 0x20].copy_from_slice(&id);
+    }
+
+    // the index table and its backup, with every data page free
+    for table in 1..=2 {
+        let table = &mut data[table * PAGE_SIZE..(table + 1) * PAGE_SIZE];
+        for entry in table.chunks_mut(2).skip(1) {
+            entry.copy_from_slice(&[0x00, 0x03]);
+        }
+        let checksum = table[FIRST_DATA_PAGE * 2..]
+            .chunks(2)
+            .fold(0u8, |sum, e| sum.wrapping_add(e[1]));
+        table[1] = checksum;
+    }
+}
+
+#[test]
+fn format_test() {
+    let pak = MemPak::new();
+    assert_eq!(&pak.data()[0x3C..0x40], &[0x66, 0x25, 0x99, 0xCD]);
+    assert_eq!(&pak.data()[0x100..0x104], &[0x00, 0x71, 0x00, 0x03]);
+    assert_eq!(pak.data()[0x200..0x300], pak.data()[0x100..0x200]);
+}
@@ -0,0 +1,113 @@
+/*
+ * src/pak/mod.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Accessories that can be plugged into a controller.
+
+mod gb_cart;
+mod mempak;
+mod mpk;
+mod rumble;
+mod transfer;
+
+pub use gb_cart::{GbCart, Mbc};
+pub use mempak::{MemPak, MEMPAK_SIZE, PAGE_SIZE};
+pub use mpk::{Mpk, MpkError, Note, NOTE_COUNT, NOTE_ENTRY_SIZE};
+pub use rumble::RumblePak;
+pub use transfer::TransferPak;
+
+use std::{io, os::raw::c_int};
+
+/// The size of a block of data read from or written to a pak.
+pub const BLOCK_SIZE: usize = 32;
+
+/// A pak that answers reads and writes when the plugin handles the controller protocol itself.
+pub trait PakDevice: Send {
+    /// Read a block of data starting at `address`.
+    fn read(&mut self, address: u16, data: &mut [u8; BLOCK_SIZE]);
+
+    /// Write a block of data starting at `address`.
+    fn write(&mut self, address: u16, data: &[u8; BLOCK_SIZE]);
+
+    /// Write any state backed by a file to disk.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A pak inserted into the slot of a controller.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Pak {
+    None,
+    MemPak,
+    RumblePak,
+    TransferPak,
+    /// The core leaves the pak to the plugin, which has no pak to answer with: the controller
+    /// reports its slot as empty, and reads and writes come back with a bad checksum.
+    Raw,
+}
+
+impl Default for Pak {
+    fn default() -> Pak {
+        Pak::None
+    }
+}
+
+impl Pak {
+    /// Every kind of pak, in the order they are presented to the user.
+    pub const ALL: [Pak; 5] = [
+        Pak::None,
+        Pak::MemPak,
+        Pak::RumblePak,
+        Pak::TransferPak,
+        Pak::Raw,
+    ];
+
+    /// A human-readable name for this pak.
+    pub fn name(self) -> &'static str {
+        match self {
+            Pak::None => "No Pak",
+            Pak::MemPak => "Controller Pak",
+            Pak::RumblePak => "Rumble Pak",
+            Pak::TransferPak => "Transfer Pak",
+            Pak::Raw => "Raw",
+        }
+    }
+
+    /// The value of the `Plugin` field of a `CONTROL` entry for this pak.
+    pub fn to_plugin(self) -> c_int {
+        (match self {
+            Pak::None => m64p_sys::PLUGIN_NONE,
+            Pak::MemPak => m64p_sys::PLUGIN_MEMPAK,
+            Pak::RumblePak => m64p_sys::PLUGIN_RUMBLE_PAK,
+            Pak::TransferPak => m64p_sys::PLUGIN_TRANSFER_PAK,
+            Pak::Raw => m64p_sys::PLUGIN_RAW,
+        }) as c_int
+    }
+
+    /// Convert the value stored in the configuration file to a pak.
+    pub fn from_int(value: i32) -> Pak {
+        Pak::ALL.get(value as usize).copied().unwrap_or(Pak::None)
+    }
+
+    /// Convert this pak to the value stored in the configuration file.
+    pub fn to_int(self) -> i32 {
+        Pak::ALL.iter().position(|p| *p == self).unwrap_or(0) as i32
+    }
+}
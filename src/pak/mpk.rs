@@ -0,0 +1,369 @@
+/*
+ * src/pak/mpk.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Reading and editing the filesystem on a Controller Pak image.
+
+use super::{mempak, MEMPAK_SIZE, PAGE_SIZE};
+use std::{fs, io, path::Path};
+use thiserror::Error;
+
+// the pages holding the index table, its backup and the note table
+const INDEX_PAGE: usize = 1;
+const BACKUP_INDEX_PAGE: usize = 2;
+const NOTE_TABLE: usize = 3 * PAGE_SIZE;
+// the pages available to notes
+const FIRST_DATA_PAGE: usize = 5;
+const PAGE_COUNT: usize = MEMPAK_SIZE / PAGE_SIZE;
+
+/// The number of notes a Controller Pak can hold.
+pub const NOTE_COUNT: usize = 16;
+/// The size of an entry in the note table.
+pub const NOTE_ENTRY_SIZE: usize = 32;
+
+// values in the index table
+const END_OF_NOTE: u8 = 0x01;
+const FREE_PAGE: u8 = 0x03;
+
+// the characters of the N64 font, starting at 0x0F
+const N64_CHARSET: &[u8] = b" 0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ!\"#'*+,-./:=?@";
+const N64_CHARSET_START: u8 = 0x0F;
+
+/// An error in a Controller Pak image.
+#[derive(Debug, Error)]
+pub enum MpkError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("Image is {0} bytes instead of {}", MEMPAK_SIZE)]
+    WrongSize(usize),
+    #[error("ID block at {0:#04X} has an invalid checksum")]
+    IdChecksum(usize),
+    #[error("Index table in page {0} has an invalid checksum")]
+    IndexChecksum(usize),
+    #[error("Index table does not match its backup")]
+    IndexMismatch,
+    #[error("Note {0} has a broken chain of pages")]
+    BrokenChain(usize),
+    #[error("There is no note {0}")]
+    NoSuchNote(usize),
+    #[error("Note table is full")]
+    NoteTableFull,
+    #[error("Note needs {needed} pages but only {free} are free")]
+    NotEnoughSpace { needed: usize, free: usize },
+    #[error("Note file is malformed")]
+    InvalidNote,
+}
+
+/// A saved game on a Controller Pak.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+    /// The position of the note in the note table.
+    pub index: usize,
+    pub game_code: [u8; 4],
+    pub publisher: [u8; 2],
+    pub name: String,
+    pub extension: String,
+    /// The pages holding the note's data, in order.
+    pub pages: Vec<usize>,
+}
+
+impl Note {
+    /// The game code and publisher as text, such as `NSME-01`.
+    pub fn game_id(&self) -> String {
+        format!(
+            "{}-{}",
+            String::from_utf8_lossy(&self.game_code),
+            String::from_utf8_lossy(&self.publisher)
+        )
+    }
+}
+
+// decode text in the N64 font
+fn decode_text(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .take_while(|b| **b != 0)
+        .map(|b| {
+            b.checked_sub(N64_CHARSET_START)
+                .and_then(|i| N64_CHARSET.get(i as usize))
+                .map_or('?', |c| *c as char)
+        })
+        .collect()
+}
+
+/// The filesystem on a Controller Pak image.
+pub struct Mpk {
+    data: Vec<u8>,
+}
+
+impl Mpk {
+    /// Wrap the contents of an image.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Mpk, MpkError> {
+        if data.len() != MEMPAK_SIZE {
+            return Err(MpkError::WrongSize(data.len()));
+        }
+        Ok(Mpk { data })
+    }
+
+    /// Create a freshly formatted image.
+    pub fn formatted() -> Mpk {
+        let mut data = vec![0; MEMPAK_SIZE];
+        mempak::format(&mut data);
+        Mpk { data }
+    }
+
+    /// Read an image from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Mpk, MpkError> {
+        Mpk::from_bytes(fs::read(path)?)
+    }
+
+    /// Write the image to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), MpkError> {
+        Ok(fs::write(path, &self.data)?)
+    }
+
+    /// The raw contents of the image.
+    pub fn bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Erase every note.
+    pub fn format(&mut self) {
+        mempak::format(&mut self.data);
+    }
+
+    // the next page after `page` in the index table
+    fn next_page(&self, page: usize) -> u8 {
+        self.data[INDEX_PAGE * PAGE_SIZE + page * 2 + 1]
+    }
+
+    // set the next page after `page` in the index table
+    fn set_next_page(&mut self, page: usize, next: u8) {
+        self.data[INDEX_PAGE * PAGE_SIZE + page * 2] = 0;
+        self.data[INDEX_PAGE * PAGE_SIZE + page * 2 + 1] = next;
+    }
+
+    // the checksum of an index table
+    fn index_checksum(&self, page: usize) -> u8 {
+        let table = &self.data[page * PAGE_SIZE..(page + 1) * PAGE_SIZE];
+        table[FIRST_DATA_PAGE * 2..]
+            .iter()
+            .fold(0u8, |sum, b| sum.wrapping_add(*b))
+    }
+
+    // update the checksum of the index table and copy it to the backup
+    fn commit_index(&mut self) {
+        let checksum = self.index_checksum(INDEX_PAGE);
+        self.data[INDEX_PAGE * PAGE_SIZE + 1] = checksum;
+        self.data.copy_within(
+            INDEX_PAGE * PAGE_SIZE..(INDEX_PAGE + 1) * PAGE_SIZE,
+            BACKUP_INDEX_PAGE * PAGE_SIZE,
+        );
+    }
+
+    // the entry for a note in the note table
+    fn note_entry(&self, index: usize) -> &[u8] {
+        let start = NOTE_TABLE + index * NOTE_ENTRY_SIZE;
+        &self.data[start..start + NOTE_ENTRY_SIZE]
+    }
+
+    // follow the chain of pages starting at `start`
+    fn chain(&self, index: usize, start: usize) -> Result<Vec<usize>, MpkError> {
+        let mut pages = Vec::new();
+        let mut page = start;
+        loop {
+            if page < FIRST_DATA_PAGE || page >= PAGE_COUNT || pages.contains(&page) {
+                return Err(MpkError::BrokenChain(index));
+            }
+            pages.push(page);
+
+            match self.next_page(page) {
+                END_OF_NOTE => return Ok(pages),
+                next => page = next as usize,
+            }
+        }
+    }
+
+    /// Get a note from the note table, if that entry is in use.
+    pub fn note(&self, index: usize) -> Result<Option<Note>, MpkError> {
+        if index >= NOTE_COUNT {
+            return Err(MpkError::NoSuchNote(index));
+        }
+
+        let entry = self.note_entry(index);
+        let start = u16::from_be_bytes([entry[6], entry[7]]) as usize;
+        if entry[..4] == [0; 4] || start == 0 {
+            return Ok(None);
+        }
+
+        let mut game_code = [0; 4];
+        game_code.copy_from_slice(&entry[..4]);
+        Ok(Some(Note {
+            index,
+            game_code,
+            publisher: [entry[4], entry[5]],
+            name: decode_text(&entry[0x10..0x20]),
+            extension: decode_text(&entry[0x0C..0x10]),
+            pages: self.chain(index, start)?,
+        }))
+    }
+
+    /// Get every note in use.
+    pub fn notes(&self) -> Result<Vec<Note>, MpkError> {
+        let mut notes = Vec::new();
+        for index in 0..NOTE_COUNT {
+            notes.extend(self.note(index)?);
+        }
+        Ok(notes)
+    }
+
+    /// The number of pages not used by any note.
+    pub fn free_pages(&self) -> usize {
+        (FIRST_DATA_PAGE..PAGE_COUNT)
+            .filter(|p| self.next_page(*p) == FREE_PAGE)
+            .count()
+    }
+
+    /// Find problems with the ID blocks, index tables and notes.
+    pub fn validate(&self) -> Vec<MpkError> {
+        let mut problems = Vec::new();
+
+        for offset in [0x20, 0x60, 0x80, 0xC0].iter() {
+            let block = &self.data[*offset..*offset + 0x20];
+            let (sum, inverse) = mempak::id_checksum(block);
+            if block[0x1C..0x1E] != sum.to_be_bytes() || block[0x1E..] != inverse.to_be_bytes() {
+                problems.push(MpkError::IdChecksum(*offset));
+            }
+        }
+
+        for page in [INDEX_PAGE, BACKUP_INDEX_PAGE].iter() {
+            if self.data[page * PAGE_SIZE + 1] != self.index_checksum(*page) {
+                problems.push(MpkError::IndexChecksum(*page));
+            }
+        }
+        let (index, backup) = (
+            &self.data[INDEX_PAGE * PAGE_SIZE..(INDEX_PAGE + 1) * PAGE_SIZE],
+            &self.data[BACKUP_INDEX_PAGE * PAGE_SIZE..(BACKUP_INDEX_PAGE + 1) * PAGE_SIZE],
+        );
+        if index != backup {
+            problems.push(MpkError::IndexMismatch);
+        }
+
+        for index in 0..NOTE_COUNT {
+            if let Err(e) = self.note(index) {
+                problems.push(e);
+            }
+        }
+
+        problems
+    }
+
+    /// Export a note as its note table entry followed by its pages.
+    pub fn export_note(&self, index: usize) -> Result<Vec<u8>, MpkError> {
+        let note = self.note(index)?.ok_or(MpkError::NoSuchNote(index))?;
+
+        let mut exported = self.note_entry(index).to_vec();
+        for page in note.pages {
+            exported.extend_from_slice(&self.data[page * PAGE_SIZE..(page + 1) * PAGE_SIZE]);
+        }
+        Ok(exported)
+    }
+
+    /// Import a note exported by `export_note`, returning its index in the note table.
+    pub fn import_note(&mut self, exported: &[u8]) -> Result<usize, MpkError> {
+        if exported.len() <= NOTE_ENTRY_SIZE || (exported.len() - NOTE_ENTRY_SIZE) % PAGE_SIZE != 0
+        {
+            return Err(MpkError::InvalidNote);
+        }
+        let (entry, contents) = exported.split_at(NOTE_ENTRY_SIZE);
+
+        let index = (0..NOTE_COUNT)
+            .find(|i| self.note_entry(*i)[..4] == [0; 4])
+            .ok_or(MpkError::NoteTableFull)?;
+
+        let needed = contents.len() / PAGE_SIZE;
+        let free: Vec<usize> = (FIRST_DATA_PAGE..PAGE_COUNT)
+            .filter(|p| self.next_page(*p) == FREE_PAGE)
+            .take(needed)
+            .collect();
+        if free.len() < needed {
+            return Err(MpkError::NotEnoughSpace {
+                needed,
+                free: self.free_pages(),
+            });
+        }
+
+        // copy the data and chain the pages together
+        for (i, (page, data)) in free.iter().zip(contents.chunks(PAGE_SIZE)).enumerate() {
+            self.data[page * PAGE_SIZE..(page + 1) * PAGE_SIZE].copy_from_slice(data);
+            let next = free.get(i + 1).map_or(END_OF_NOTE, |p| *p as u8);
+            self.set_next_page(*page, next);
+        }
+        self.commit_index();
+
+        let start = NOTE_TABLE + index * NOTE_ENTRY_SIZE;
+        self.data[start..start + NOTE_ENTRY_SIZE].copy_from_slice(entry);
+        self.data[start + 6..start + 8].copy_from_slice(&(free[0] as u16).to_be_bytes());
+        Ok(index)
+    }
+
+    /// Remove a note, freeing its pages.
+    pub fn delete_note(&mut self, index: usize) -> Result<(), MpkError> {
+        let note = self.note(index)?.ok_or(MpkError::NoSuchNote(index))?;
+
+        for page in note.pages {
+            self.set_next_page(page, FREE_PAGE);
+        }
+        self.commit_index();
+
+        let start = NOTE_TABLE + index * NOTE_ENTRY_SIZE;
+        self.data[start..start + NOTE_ENTRY_SIZE]
+            .iter_mut()
+            .for_each(|b| *b = 0);
+        Ok(())
+    }
+}
+
+#[test]
+fn note_roundtrip_test() {
+    let mut mpk = Mpk::formatted();
+    assert!(mpk.validate().is_empty());
+    assert_eq!(mpk.free_pages(), PAGE_COUNT - FIRST_DATA_PAGE);
+
+    // a two page note named "MARIO"
+    let mut exported = vec![0; NOTE_ENTRY_SIZE + PAGE_SIZE * 2];
+    exported[..6].copy_from_slice(b"NSME01");
+    exported[8] = 0x02;
+    exported[0x10..0x15].copy_from_slice(&[0x26, 0x1A, 0x2B, 0x22, 0x28]);
+    exported[NOTE_ENTRY_SIZE + PAGE_SIZE] = 0x42;
+
+    let index = mpk.import_note(&exported).unwrap();
+    let note = mpk.note(index).unwrap().unwrap();
+    assert_eq!(note.game_id(), "NSME-01");
+    assert_eq!(note.name, "MARIO");
+    assert_eq!(note.pages.len(), 2);
+    assert!(mpk.validate().is_empty());
+
+    let reexported = mpk.export_note(index).unwrap();
+    assert_eq!(&reexported[NOTE_ENTRY_SIZE..], &exported[NOTE_ENTRY_SIZE..]);
+
+    mpk.delete_note(index).unwrap();
+    assert!(mpk.notes().unwrap().is_empty());
+    assert_eq!(mpk.free_pages(), PAGE_COUNT - FIRST_DATA_PAGE);
+}
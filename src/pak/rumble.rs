@@ -0,0 +1,72 @@
+/*
+ * src/pak/rumble.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{PakDevice, BLOCK_SIZE};
+use std::sync::{Arc, Mutex};
+
+// reads from this range identify the pak as a rumble pak
+const IDENTIFY: u16 = 0x8000;
+// writes to this range switch the motor on or off
+const MOTOR: u16 = 0xC000;
+
+/// A Rumble Pak, which reports the state of its motor through a shared flag.
+pub struct RumblePak {
+    motor: Arc<Mutex<bool>>,
+}
+
+impl RumblePak {
+    /// Create a Rumble Pak whose motor is reflected in `motor`.
+    pub fn new(motor: Arc<Mutex<bool>>) -> RumblePak {
+        *motor.lock().unwrap() = false;
+        RumblePak { motor }
+    }
+}
+
+impl PakDevice for RumblePak {
+    fn read(&mut self, address: u16, data: &mut [u8; BLOCK_SIZE]) {
+        let value = if address & 0xF000 == IDENTIFY {
+            0x80
+        } else {
+            0x00
+        };
+        data.iter_mut().for_each(|b| *b = value);
+    }
+
+    fn write(&mut self, address: u16, data: &[u8; BLOCK_SIZE]) {
+        if address & 0xF000 == MOTOR {
+            *self.motor.lock().unwrap() = data[BLOCK_SIZE - 1] & 0x01 != 0;
+        }
+    }
+}
+
+#[test]
+fn rumble_test() {
+    let motor = Arc::new(Mutex::new(false));
+    let mut pak = RumblePak::new(motor.clone());
+
+    let mut data = [0; BLOCK_SIZE];
+    pak.read(0x8000, &mut data);
+    assert_eq!(data, [0x80; BLOCK_SIZE]);
+
+    pak.write(0xC000, &[0x01; BLOCK_SIZE]);
+    assert!(*motor.lock().unwrap());
+    pak.write(0xC000, &[0x00; BLOCK_SIZE]);
+    assert!(!*motor.lock().unwrap());
+}
@@ -0,0 +1,137 @@
+/*
+ * src/pak/transfer.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{GbCart, PakDevice, BLOCK_SIZE};
+use std::io;
+
+// the regions of the pak's address space, selected by the top four bits
+const POWER: u16 = 0x8;
+const BANK: u16 = 0xA;
+const STATUS: u16 = 0xB;
+
+// values written to and read from the power register
+const POWER_ON: u8 = 0x84;
+const POWER_OFF: u8 = 0xFE;
+
+// bits in the status register
+const STATUS_ACCESS: u8 = 0x01;
+const STATUS_READY: u8 = 0x80;
+const STATUS_ACCESS_CHANGED: u8 = 0x04;
+const STATUS_NO_CART: u8 = 0x40;
+const STATUS_ACCESS_ON: u8 = 0x08;
+
+/// A Transfer Pak, giving the console access to a Game Boy cartridge.
+pub struct TransferPak {
+    cart: Option<GbCart>,
+    powered: bool,
+    access: bool,
+    access_changed: bool,
+    // which 16 KiB window of the cartridge is mapped at 0xC000
+    bank: u16,
+}
+
+impl TransferPak {
+    /// Create a Transfer Pak holding a cartridge, if any.
+    pub fn new(cart: Option<GbCart>) -> TransferPak {
+        TransferPak {
+            cart,
+            powered: false,
+            access: false,
+            access_changed: false,
+            bank: 0,
+        }
+    }
+
+    // the address in the cartridge for an address in 0xC000..0x10000
+    fn cart_address(&self, address: u16, offset: usize) -> u16 {
+        (address & 0x3FFF)
+            .wrapping_add(offset as u16)
+            .wrapping_add(self.bank.wrapping_mul(0x4000))
+    }
+}
+
+impl PakDevice for TransferPak {
+    fn read(&mut self, address: u16, data: &mut [u8; BLOCK_SIZE]) {
+        match address >> 12 {
+            POWER => {
+                let value = if self.powered { POWER_ON } else { 0x00 };
+                data.iter_mut().for_each(|b| *b = value);
+            }
+            STATUS => {
+                let mut value = if !self.powered {
+                    0x00
+                } else if self.cart.is_none() {
+                    STATUS_NO_CART
+                } else if self.access {
+                    STATUS_READY | STATUS_ACCESS_ON | STATUS_ACCESS
+                } else {
+                    STATUS_READY
+                };
+                if self.access_changed {
+                    value |= STATUS_ACCESS_CHANGED;
+                    self.access_changed = false;
+                }
+                data.iter_mut().for_each(|b| *b = value);
+            }
+            0xC..=0xF if self.powered && self.access => {
+                for (i, b) in data.iter_mut().enumerate() {
+                    let cart_address = self.cart_address(address, i);
+                    *b = self.cart.as_ref().map_or(0xFF, |c| c.read(cart_address));
+                }
+            }
+            _ => data.iter_mut().for_each(|b| *b = 0x00),
+        }
+    }
+
+    fn write(&mut self, address: u16, data: &[u8; BLOCK_SIZE]) {
+        let value = data[BLOCK_SIZE - 1];
+        match address >> 12 {
+            POWER => match value {
+                POWER_ON => self.powered = true,
+                POWER_OFF => self.powered = false,
+                _ => {}
+            },
+            BANK if self.powered => self.bank = (value & 0x03) as u16,
+            STATUS if self.powered => {
+                let access = value & STATUS_ACCESS != 0;
+                if access != self.access {
+                    self.access_changed = true;
+                }
+                self.access = access;
+            }
+            0xC..=0xF if self.powered && self.access => {
+                for (i, b) in data.iter().enumerate() {
+                    let cart_address = self.cart_address(address, i);
+                    if let Some(ref mut cart) = self.cart {
+                        cart.write(cart_address, *b);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.cart {
+            Some(ref mut cart) => cart.save(),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,746 @@
+/*
+ * src/piano_roll.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::{
+    branch_panel::BranchPanel,
+    breakpoint_panel::BreakpointPanel,
+    core_lib::Core,
+    movie::{empty_frame, Marker, Movie, MovieState, RecordMode},
+    state::SharedState,
+    Button, Inputs, Interpolation, Keyframe, Ramp, CONTROLLER_COUNT,
+};
+use qt_widgets::{
+    cpp_core::{CppBox, MutPtr},
+    q_abstract_item_view::SelectionMode,
+    qt_core::{GlobalColor, ItemFlag, QString, QStringList, QTimer, Slot, SlotOfInt, SlotOfIntInt},
+    qt_gui::{QBrush, QKeySequence},
+    QCheckBox, QComboBox, QFileDialog, QHBoxLayout, QLabel, QPushButton, QShortcut, QSpinBox,
+    QTabWidget, QTableWidget, QTableWidgetItem, QVBoxLayout, QWidget,
+};
+use std::{
+    cell::Cell,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+// a column for each button, in the order of `Button::ALL`, followed by the two stick columns and
+// the marker on the frame
+const X_COLUMN: i32 = 14;
+const Y_COLUMN: i32 = 15;
+const MARKER_COLUMN: i32 = 16;
+const COMMENT_COLUMN: i32 = 17;
+const COLUMN_COUNT: i32 = 18;
+
+const MOVIE_FILTER: &str = "Movies (*.m64)";
+
+/// The hotkey moving to the next marker in the movie.
+pub const NEXT_MARKER_KEY: &str = "Ctrl+]";
+/// The hotkey moving to the previous marker in the movie.
+pub const PREVIOUS_MARKER_KEY: &str = "Ctrl+[";
+
+// the text of a cell, the button name when it is pressed
+fn cell_text(inputs: &Inputs, column: i32) -> String {
+    match column {
+        X_COLUMN => inputs.x.to_string(),
+        Y_COLUMN => inputs.y.to_string(),
+        _ => {
+            let button = Button::ALL[column as usize];
+            if inputs.button(button) {
+                button.name().to_string()
+            } else {
+                String::new()
+            }
+        }
+    }
+}
+
+// the text of a marker cell, empty on frames without a marker
+fn marker_text(marker: Option<&Marker>, column: i32) -> String {
+    match (marker, column) {
+        (Some(marker), MARKER_COLUMN) => marker.name.clone(),
+        (Some(marker), _) => marker.comment.clone(),
+        (None, _) => String::new(),
+    }
+}
+
+// write the inputs and marker of one frame into a row, creating its cells
+unsafe fn show_row(
+    mut table: MutPtr<QTableWidget>,
+    row: i32,
+    inputs: &Inputs,
+    marker: Option<&Marker>,
+) {
+    for column in 0..COLUMN_COUNT {
+        let text = if column < MARKER_COLUMN {
+            cell_text(inputs, column)
+        } else {
+            marker_text(marker, column)
+        };
+        let mut item = QTableWidgetItem::from_q_string(&QString::from_std_str(text));
+        // buttons are toggled by clicking, only the stick and the marker are typed in
+        if column < X_COLUMN {
+            item.set_flags(ItemFlag::ItemIsEnabled.into());
+        } else {
+            item.set_flags(ItemFlag::ItemIsEnabled | ItemFlag::ItemIsEditable);
+        }
+        table.set_item(row, column, item.into_ptr());
+    }
+}
+
+/// A table of every frame in the movie, with a row per frame and a column per input.
+#[allow(dead_code)]
+pub struct PianoRoll<'a> {
+    base_window: CppBox<QWidget>,
+    movie: &'a Arc<Mutex<Movie>>,
+
+    port_select: MutPtr<QComboBox>,
+    table: MutPtr<QTableWidget>,
+    branch_panel: BranchPanel<'a>,
+    breakpoint_panel: BreakpointPanel<'a>,
+    jump: MutPtr<QSpinBox>,
+    jump_button: MutPtr<QPushButton>,
+    seek_button: MutPtr<QPushButton>,
+    ramp_to: MutPtr<QSpinBox>,
+    interpolation_select: MutPtr<QComboBox>,
+    ramp_button: MutPtr<QPushButton>,
+    open_button: MutPtr<QPushButton>,
+    save_button: MutPtr<QPushButton>,
+    add_button: MutPtr<QPushButton>,
+    play_button: MutPtr<QPushButton>,
+    record_button: MutPtr<QPushButton>,
+    mode_select: MutPtr<QComboBox>,
+    recorded_checks: [MutPtr<QCheckBox>; CONTROLLER_COUNT],
+    refresh_timer: CppBox<QTimer>,
+    undo_shortcut: MutPtr<QShortcut>,
+    redo_shortcut: MutPtr<QShortcut>,
+    next_marker_shortcut: MutPtr<QShortcut>,
+    previous_marker_shortcut: MutPtr<QShortcut>,
+
+    port_changed: SlotOfInt<'a>,
+    cell_pressed: SlotOfIntInt<'a>,
+    cell_entered: SlotOfIntInt<'a>,
+    cell_changed: SlotOfIntInt<'a>,
+    jump_clicked: Slot<'a>,
+    seek_clicked: Slot<'a>,
+    ramp_clicked: Slot<'a>,
+    open_clicked: Slot<'a>,
+    save_clicked: Slot<'a>,
+    add_clicked: Slot<'a>,
+    play_clicked: Slot<'a>,
+    record_clicked: Slot<'a>,
+    mode_changed: SlotOfInt<'a>,
+    recorded_clicked: Slot<'a>,
+    refresh: Slot<'a>,
+    undo_activated: Slot<'a>,
+    redo_activated: Slot<'a>,
+    next_marker_activated: Slot<'a>,
+    previous_marker_activated: Slot<'a>,
+}
+
+impl<'a> PianoRoll<'a> {
+    /// Instantiate a new piano roll editing the movie in the shared state.
+    ///
+    /// Branches can only be saved and loaded with a `core` to make savestates with.
+    pub fn new(shared: &'a SharedState, core: Option<&'a Core>) -> PianoRoll<'a> {
+        let movie_reference = &shared.movie;
+        let frame_reference = &shared.frame;
+
+        let mut base_window = unsafe { QWidget::new_0a() };
+        unsafe { base_window.set_window_title(&QString::from_std_str("TAS Input - Piano Roll")) };
+        let window_ptr = unsafe { base_window.as_mut_ptr() };
+        let mut layout = unsafe { QVBoxLayout::new_1a(&mut base_window).into_ptr() };
+
+        // the port being edited, the file and the frame to jump to
+        let mut toolbar = unsafe { QWidget::new_0a() };
+        let mut toolbar_layout = unsafe { QHBoxLayout::new_1a(&mut toolbar).into_ptr() };
+        let mut button = move |text: &str| unsafe {
+            let mut button = QPushButton::from_q_string(&QString::from_std_str(text));
+            toolbar_layout.add_widget(&mut button);
+            button.into_ptr()
+        };
+        let open_button = button("Open...");
+        let save_button = button("Save...");
+        let add_button = button("Add Frame");
+        let mut play_button = button("Play");
+        let mut record_button = button("Record");
+        let mode_select = unsafe {
+            play_button.set_checkable(true);
+            record_button.set_checkable(true);
+
+            let mut mode_select = QComboBox::new_0a();
+            for mode in RecordMode::ALL.iter() {
+                mode_select.add_item_q_string(&QString::from_std_str(mode.name()));
+            }
+            mode_select.set_current_index(shared.record_mode.lock().unwrap().to_int());
+            toolbar_layout.add_widget(&mut mode_select);
+            mode_select.into_ptr()
+        };
+
+        // the ports that are left unchecked play back while the others are recorded
+        let recorded_checks: [MutPtr<QCheckBox>; CONTROLLER_COUNT] = unsafe {
+            let mut label = QLabel::from_q_string(&QString::from_std_str("Record ports"));
+            toolbar_layout.add_widget(&mut label);
+            label.into_ptr();
+
+            let recorded = *shared.recorded_ports.lock().unwrap();
+            array_init::array_init(|port| {
+                let mut check =
+                    QCheckBox::from_q_string(&QString::from_std_str((port + 1).to_string()));
+                check.set_checked(recorded[port]);
+                toolbar_layout.add_widget(&mut check);
+                check.into_ptr()
+            })
+        };
+        let (port_select, jump) = unsafe {
+            let mut port_select = QComboBox::new_0a();
+            for port in 0..CONTROLLER_COUNT {
+                port_select.add_item_q_string(&QString::from_std_str(format!("Port {}", port + 1)));
+            }
+            toolbar_layout.add_widget(&mut port_select);
+
+            let mut jump_label = QLabel::from_q_string(&QString::from_std_str("Frame"));
+            let mut jump = QSpinBox::new_0a();
+            jump.set_maximum(i32::MAX);
+            toolbar_layout.add_widget(&mut jump_label);
+            toolbar_layout.add_widget(&mut jump);
+            jump_label.into_ptr();
+            (port_select.into_ptr(), jump.into_ptr())
+        };
+        let jump_button = button("Go");
+        let seek_button = button("Seek");
+        let (ramp_to, interpolation_select) = unsafe {
+            let mut ramp_label = QLabel::from_q_string(&QString::from_std_str("Ramp To"));
+            let mut ramp_to = QSpinBox::new_0a();
+            ramp_to.set_maximum(i32::MAX);
+            toolbar_layout.add_widget(&mut ramp_label);
+            toolbar_layout.add_widget(&mut ramp_to);
+            ramp_label.into_ptr();
+
+            let mut interpolation_select = QComboBox::new_0a();
+            for interpolation in Interpolation::ALL.iter() {
+                interpolation_select
+                    .add_item_q_string(&QString::from_std_str(interpolation.name()));
+            }
+            toolbar_layout.add_widget(&mut interpolation_select);
+            (ramp_to.into_ptr(), interpolation_select.into_ptr())
+        };
+        let ramp_button = button("Ramp");
+        unsafe { layout.add_widget(toolbar.into_ptr()) };
+
+        // the frames, with the branches and breakpoints next to them
+        let mut body = unsafe { QWidget::new_0a() };
+        let mut body_layout = unsafe { QHBoxLayout::new_1a(&mut body).into_ptr() };
+        unsafe { layout.add_widget(body.into_ptr()) };
+
+        let mut table = unsafe {
+            let mut table = QTableWidget::new_0a();
+            table.set_column_count(COLUMN_COUNT);
+            let mut labels = QStringList::new();
+            let names = Button::ALL.iter().map(|b| b.name());
+            for name in names.chain(["X", "Y", "Marker", "Comment"].iter().copied()) {
+                labels.append_q_string(&QString::from_std_str(name));
+            }
+            table.set_horizontal_header_labels(&labels);
+            table.set_selection_mode(SelectionMode::NoSelection);
+            body_layout.add_widget(&mut table);
+            table.into_ptr()
+        };
+        let mut branch_panel = BranchPanel::new(shared, core);
+        let mut breakpoint_panel = BreakpointPanel::new(shared);
+        unsafe {
+            let mut tabs = QTabWidget::new_0a();
+            tabs.add_tab_2a(
+                branch_panel.container.as_mut_ptr(),
+                &QString::from_std_str("Branches"),
+            );
+            tabs.add_tab_2a(
+                breakpoint_panel.container.as_mut_ptr(),
+                &QString::from_std_str("Breakpoints"),
+            );
+            body_layout.add_widget(tabs.into_ptr());
+        }
+
+        // rebuild every row from the movie, for the port that is selected
+        let highlighted: Rc<Cell<Option<i32>>> = Rc::new(Cell::new(None));
+        let fill = {
+            let highlighted = highlighted.clone();
+            move || unsafe {
+                let mut table = table;
+                let port = port_select.current_index() as usize;
+                let movie = movie_reference.lock().unwrap();
+                table.block_signals(true);
+                table.set_row_count(movie.len() as i32);
+                for (row, frame) in movie.frames().iter().enumerate() {
+                    let marker = movie.markers.at(row as u64);
+                    show_row(table, row as i32, &frame[port], marker);
+                }
+                table.block_signals(false);
+                highlighted.set(None);
+            }
+        };
+        let port_changed = unsafe { SlotOfInt::new(move |_| fill()) };
+
+        let edit_cell = move |row: i32,
+                              column: i32,
+                              group: Option<u64>,
+                              edit: &dyn Fn(&mut Movie, usize)| unsafe {
+            let mut table = table;
+            let port = port_select.current_index() as usize;
+            shared.edit_movie(group, |movie| edit(movie, port));
+
+            // show what was actually stored
+            let inputs = movie_reference
+                .lock()
+                .unwrap()
+                .frame(row as usize)
+                .map(|frame| frame[port]);
+            if let Some(inputs) = inputs {
+                table.block_signals(true);
+                table
+                    .item(row, column)
+                    .set_text(&QString::from_std_str(cell_text(&inputs, column)));
+                table.block_signals(false);
+            }
+        };
+
+        // clicking a button cell toggles it, and dragging down the column paints the same
+        // value, undone in one step
+        let painting: Rc<Cell<Option<(i32, bool, u64)>>> = Rc::new(Cell::new(None));
+        let paint = move |row: i32, column: i32, pressed: bool, group: u64| {
+            let button = Button::ALL[column as usize];
+            let row = row as usize;
+            edit_cell(row as i32, column, Some(group), &|movie, port| {
+                movie.set_button(port, button, row..row + 1, pressed);
+            });
+        };
+        let cell_pressed = unsafe {
+            let painting = painting.clone();
+            let paint = paint.clone();
+            SlotOfIntInt::new(move |row, column| {
+                if column >= X_COLUMN {
+                    painting.set(None);
+                    return;
+                }
+                let port = port_select.current_index() as usize;
+                let pressed = match movie_reference.lock().unwrap().frame(row as usize) {
+                    Some(frame) => !frame[port].button(Button::ALL[column as usize]),
+                    None => return,
+                };
+                let group = shared.history.lock().unwrap().group();
+                painting.set(Some((column, pressed, group)));
+                paint(row, column, pressed, group);
+            })
+        };
+        let cell_entered = unsafe {
+            SlotOfIntInt::new(move |row, column| {
+                if let Some((paint_column, pressed, group)) = painting.get() {
+                    if paint_column == column {
+                        paint(row, column, pressed, group);
+                    }
+                }
+            })
+        };
+
+        // typing into a stick cell moves the stick on that frame, and typing into a marker cell
+        // names or comments the marker on it, removed once both are empty
+        let cell_changed = unsafe {
+            SlotOfIntInt::new(move |row, column| {
+                if column < X_COLUMN {
+                    return;
+                }
+                if column >= MARKER_COLUMN {
+                    let text = |column| table.item(row, column).text().to_std_string();
+                    let marker = Marker {
+                        frame: row as u64,
+                        name: text(MARKER_COLUMN).trim().to_string(),
+                        comment: text(COMMENT_COLUMN).trim().to_string(),
+                    };
+                    shared.edit_movie(None, |movie| {
+                        if marker.name.is_empty() && marker.comment.is_empty() {
+                            movie.markers.remove(marker.frame);
+                        } else {
+                            movie.markers.set(marker);
+                        }
+                    });
+                    return;
+                }
+                let text = table.item(row, column).text().to_std_string();
+                let value = text.trim().parse::<i32>().ok();
+                let row = row as usize;
+                edit_cell(row as i32, column, None, &|movie, port| {
+                    let inputs = match (value, movie.frame(row)) {
+                        (Some(_), Some(frame)) => frame[port],
+                        _ => return,
+                    };
+                    let value = value.unwrap().max(-128).min(127) as i8;
+                    let (x, y) = if column == X_COLUMN {
+                        (value, inputs.y)
+                    } else {
+                        (inputs.x, value)
+                    };
+                    movie.set_stick(port, row..row + 1, x, y);
+                });
+            })
+        };
+
+        let scroll_to_jump = move || unsafe {
+            let mut table = table;
+            let row = jump.value().min(table.row_count() - 1);
+            if row >= 0 {
+                table.scroll_to_item_1a(table.item(row, 0));
+            }
+        };
+        let jump_clicked = unsafe { Slot::new(scroll_to_jump) };
+
+        // ctrl + ] and ctrl + [ move the frame to jump to onto the next or previous marker
+        let go_to_marker = move |next: bool| unsafe {
+            let mut jump = jump;
+            let frame = jump.value() as u64;
+            let movie = movie_reference.lock().unwrap();
+            let marker = if next {
+                movie.markers.next(frame)
+            } else {
+                movie.markers.previous(frame)
+            };
+            if let Some(marker) = marker.map(|m| m.frame) {
+                drop(movie);
+                jump.set_value(marker as i32);
+                scroll_to_jump();
+            }
+        };
+        let next_marker_activated = unsafe { Slot::new(move || go_to_marker(true)) };
+        let previous_marker_activated = unsafe { Slot::new(move || go_to_marker(false)) };
+
+        // playing the movie to the frame, from the greenzone when that is closer
+        let seek_clicked = unsafe {
+            Slot::new(move || {
+                let target = jump.value() as u64;
+                match core.map(|core| shared.seek(core, target)) {
+                    Some(Ok(())) => (),
+                    Some(Err(e)) => dprintln!("Unable to seek to frame {}: {}", target, e),
+                    None => dprintln!("Unable to seek without the emulator core"),
+                }
+            })
+        };
+
+        // ramping fills in the stick between the frame to jump to and the frame to ramp to, from
+        // the stick positions already on those two frames
+        let ramp_clicked = unsafe {
+            Slot::new(move || {
+                let port = port_select.current_index() as usize;
+                let (from, to) = (jump.value() as usize, ramp_to.value() as usize);
+                let interpolation =
+                    Interpolation::ALL[interpolation_select.current_index().max(0) as usize];
+                let clamp = *shared.clamp.lock().unwrap();
+                shared.edit_movie(None, |movie| {
+                    let keyframe = |frame: usize| {
+                        let inputs = movie.frame(frame)?[port];
+                        Some(Keyframe::new(frame as u64, inputs.x, inputs.y))
+                    };
+                    if let (Some(start), Some(end)) = (keyframe(from), keyframe(to)) {
+                        movie.set_ramp(port, &Ramp::new(start, end, interpolation, clamp));
+                    }
+                });
+            })
+        };
+
+        let open_clicked = unsafe {
+            Slot::new(move || {
+                let path = QFileDialog::get_open_file_name_4a(
+                    window_ptr,
+                    &QString::from_std_str("Open Movie"),
+                    &QString::new(),
+                    &QString::from_std_str(MOVIE_FILTER),
+                )
+                .to_std_string();
+                if path.is_empty() {
+                    return;
+                }
+                // the controllers and paks in its header are plugged in on the next frame
+                match Movie::load(&path) {
+                    Ok(opened) => {
+                        shared.edit_movie(None, |movie| *movie = opened);
+                        *shared.header_pending.lock().unwrap() = true;
+                    }
+                    Err(e) => dprintln!("Unable to open movie {}: {}", path, e),
+                }
+            })
+        };
+        let save_clicked = unsafe {
+            Slot::new(move || {
+                let path = QFileDialog::get_save_file_name_4a(
+                    window_ptr,
+                    &QString::from_std_str("Save Movie"),
+                    &QString::new(),
+                    &QString::from_std_str(MOVIE_FILTER),
+                )
+                .to_std_string();
+                if path.is_empty() {
+                    return;
+                }
+                if let Err(e) = movie_reference.lock().unwrap().save(&path) {
+                    dprintln!("Unable to save movie {}: {}", path, e);
+                }
+            })
+        };
+
+        // new frames start out as a copy of the last one
+        let add_clicked = unsafe {
+            Slot::new(move || {
+                shared.edit_movie(None, |movie| {
+                    let last = movie.frames().last().copied();
+                    let len = movie.len();
+                    movie.insert_frames(len, vec![last.unwrap_or_else(empty_frame)]);
+                });
+            })
+        };
+
+        // playing and recording are stopped by clicking the button again, and playing from the
+        // start plugs in the controllers and paks in the header again
+        let play_clicked = unsafe {
+            Slot::new(move || {
+                *shared.movie_state.lock().unwrap() = if play_button.is_checked() {
+                    if *frame_reference.lock().unwrap() == 0 {
+                        *shared.header_pending.lock().unwrap() = true;
+                    }
+                    MovieState::Playing
+                } else {
+                    MovieState::Stopped
+                };
+            })
+        };
+        // a whole recording is undone in one step
+        let record_clicked = unsafe {
+            Slot::new(move || {
+                *shared.movie_state.lock().unwrap() = if record_button.is_checked() {
+                    MovieState::Recording(shared.history.lock().unwrap().group())
+                } else {
+                    MovieState::Stopped
+                };
+            })
+        };
+        let mode_changed = unsafe {
+            SlotOfInt::new(move |index| {
+                *shared.record_mode.lock().unwrap() = RecordMode::from_int(index);
+            })
+        };
+
+        let recorded_clicked = unsafe {
+            Slot::new(move || {
+                let mut recorded = shared.recorded_ports.lock().unwrap();
+                for (port, check) in recorded_checks.iter().enumerate() {
+                    recorded[port] = check.is_checked();
+                }
+            })
+        };
+
+        // follow the frame being sent to the emulator, and show the frames changed since the last
+        // time again, along with any rows added or removed at the end
+        let refresh = unsafe {
+            Slot::new(move || {
+                // playback stops by itself at the end of the movie
+                let movie_state = *shared.movie_state.lock().unwrap();
+                let mut play_button = play_button;
+                let mut record_button = record_button;
+                play_button.set_checked(movie_state == MovieState::Playing);
+                record_button.set_checked(matches!(movie_state, MovieState::Recording(_)));
+
+                let mut table = table;
+                let changed = shared.changed_frames.lock().unwrap().take();
+                let movie = movie_reference.lock().unwrap();
+                let (count, len) = (table.row_count() as usize, movie.len());
+                let mut rows = match changed {
+                    Some(rows) => rows.start.min(len)..rows.end.min(len),
+                    None => len..len,
+                };
+                if count < len {
+                    rows = rows.start.min(count)..len;
+                }
+                if count != len || !rows.is_empty() {
+                    let port = port_select.current_index() as usize;
+                    table.block_signals(true);
+                    table.set_row_count(len as i32);
+                    for row in rows.clone() {
+                        let marker = movie.markers.at(row as u64);
+                        show_row(table, row as i32, &movie.frames()[row][port], marker);
+                    }
+                    table.block_signals(false);
+                }
+                drop(movie);
+                if let Some(row) = highlighted.get() {
+                    if rows.contains(&(row as usize)) || row as usize >= len {
+                        highlighted.set(None);
+                    }
+                }
+
+                let current = *frame_reference.lock().unwrap() as i32;
+                if highlighted.get() == Some(current) {
+                    return;
+                }
+                if let Some(row) = highlighted.get() {
+                    for column in 0..COLUMN_COUNT {
+                        let mut item = table.item(row, column);
+                        if !item.is_null() {
+                            item.set_background(&QBrush::new());
+                        }
+                    }
+                }
+                if current < table.row_count() {
+                    let brush = QBrush::from_global_color(GlobalColor::Yellow);
+                    for column in 0..COLUMN_COUNT {
+                        table.item(current, column).set_background(&brush);
+                    }
+                    highlighted.set(Some(current));
+                } else {
+                    highlighted.set(None);
+                }
+            })
+        };
+        // the same undo and redo hotkeys as the controller windows
+        let shortcut = |sequence: &str| unsafe {
+            QShortcut::new_2a(
+                &QKeySequence::from_q_string(&QString::from_std_str(sequence)),
+                window_ptr,
+            )
+            .into_ptr()
+        };
+        let undo_shortcut = shortcut("Ctrl+Z");
+        let redo_shortcut = shortcut("Ctrl+Shift+Z");
+        let next_marker_shortcut = shortcut(NEXT_MARKER_KEY);
+        let previous_marker_shortcut = shortcut(PREVIOUS_MARKER_KEY);
+        let undo_activated = unsafe {
+            Slot::new(move || {
+                shared.undo();
+            })
+        };
+        let redo_activated = unsafe {
+            Slot::new(move || {
+                shared.redo();
+            })
+        };
+
+        let mut refresh_timer = unsafe { QTimer::new_0a() };
+        unsafe { refresh_timer.start_1a(50) };
+
+        let piano_roll = PianoRoll {
+            base_window,
+            movie: movie_reference,
+
+            port_select,
+            table,
+            branch_panel,
+            breakpoint_panel,
+            jump,
+            jump_button,
+            seek_button,
+            ramp_to,
+            interpolation_select,
+            ramp_button,
+            open_button,
+            save_button,
+            add_button,
+            play_button,
+            record_button,
+            mode_select,
+            recorded_checks,
+            refresh_timer,
+            undo_shortcut,
+            redo_shortcut,
+            next_marker_shortcut,
+            previous_marker_shortcut,
+
+            port_changed,
+            cell_pressed,
+            cell_entered,
+            cell_changed,
+            jump_clicked,
+            seek_clicked,
+            ramp_clicked,
+            open_clicked,
+            save_clicked,
+            add_clicked,
+            play_clicked,
+            record_clicked,
+            mode_changed,
+            recorded_clicked,
+            refresh,
+            undo_activated,
+            redo_activated,
+            next_marker_activated,
+            previous_marker_activated,
+        };
+
+        unsafe {
+            port_select
+                .current_index_changed()
+                .connect(&piano_roll.port_changed);
+            table.cell_pressed().connect(&piano_roll.cell_pressed);
+            table.cell_entered().connect(&piano_roll.cell_entered);
+            table.cell_changed().connect(&piano_roll.cell_changed);
+            jump_button.clicked().connect(&piano_roll.jump_clicked);
+            seek_button.clicked().connect(&piano_roll.seek_clicked);
+            ramp_button.clicked().connect(&piano_roll.ramp_clicked);
+            open_button.clicked().connect(&piano_roll.open_clicked);
+            save_button.clicked().connect(&piano_roll.save_clicked);
+            add_button.clicked().connect(&piano_roll.add_clicked);
+            play_button.clicked().connect(&piano_roll.play_clicked);
+            record_button.clicked().connect(&piano_roll.record_clicked);
+            mode_select
+                .current_index_changed()
+                .connect(&piano_roll.mode_changed);
+            for check in recorded_checks.iter() {
+                check.clicked().connect(&piano_roll.recorded_clicked);
+            }
+            piano_roll
+                .refresh_timer
+                .timeout()
+                .connect(&piano_roll.refresh);
+            undo_shortcut
+                .activated()
+                .connect(&piano_roll.undo_activated);
+            redo_shortcut
+                .activated()
+                .connect(&piano_roll.redo_activated);
+            next_marker_shortcut
+                .activated()
+                .connect(&piano_roll.next_marker_activated);
+            previous_marker_shortcut
+                .activated()
+                .connect(&piano_roll.previous_marker_activated);
+        }
+
+        piano_roll
+    }
+
+    /// The piano roll window.
+    pub fn window(&mut self) -> MutPtr<QWidget> {
+        unsafe { self.base_window.as_mut_ptr() }
+    }
+
+    /// The slot moving to the next marker, for the same hotkey in other windows.
+    pub fn next_marker(&self) -> &Slot<'a> {
+        &self.next_marker_activated
+    }
+
+    /// The slot moving to the previous marker, for the same hotkey in other windows.
+    pub fn previous_marker(&self) -> &Slot<'a> {
+        &self.previous_marker_activated
+    }
+}
@@ -0,0 +1,320 @@
+/*
+ * src/pif/mod.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Answering the commands the console sends to a controller, for when the plugin handles the
+//! controller protocol itself instead of the core.
+
+mod trace;
+
+pub use trace::{PifTracer, TraceEntry, TraceStage};
+
+use crate::{
+    pak::{PakDevice, BLOCK_SIZE},
+    Device, Inputs,
+};
+use std::convert::TryInto;
+
+/// The size of the PIF RAM holding the commands for every channel.
+pub const PIF_RAM_SIZE: usize = 64;
+
+// commands sent by the console
+const STATUS: u8 = 0x00;
+const READ_BUTTONS: u8 = 0x01;
+const READ_PAK: u8 = 0x02;
+const WRITE_PAK: u8 = 0x03;
+const RESET: u8 = 0xFF;
+
+// flags in the last byte of a status response
+const PAK_PRESENT: u8 = 0x01;
+const PAK_ABSENT: u8 = 0x02;
+
+// the buttons a mouse reports, which are where A and B are on a controller
+const MOUSE_BUTTONS: u8 = 0xC0;
+
+// set in the receive length when no device answers
+const NO_RESPONSE: u8 = 0x80;
+
+// the value each address bit contributes to the address checksum
+const ADDRESS_XOR: [u16; 16] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x15, 0x1F, 0x0B, 0x16, 0x19, 0x07, 0x0E, 0x1C, 0x0D, 0x1A, 0x01,
+];
+
+/// Compute the checksum stored in the low five bits of a pak address.
+pub fn address_crc(address: u16) -> u8 {
+    let crc = (5..16)
+        .filter(|i| address & (1 << i) != 0)
+        .fold(0, |crc, i| crc ^ ADDRESS_XOR[i]);
+    (crc & 0x1F) as u8
+}
+
+/// Compute the checksum sent after a block of pak data.
+pub fn data_crc(data: &[u8; BLOCK_SIZE]) -> u8 {
+    let mut crc: u8 = 0;
+    // the checksum is shifted through one extra byte of zeroes at the end
+    for byte in data.iter().chain(std::iter::once(&0)) {
+        for bit in (0..8).rev() {
+            let xor = if crc & 0x80 != 0 { 0x85 } else { 0 };
+            crc = (crc << 1) | ((byte >> bit) & 1);
+            crc ^= xor;
+        }
+    }
+    crc
+}
+
+/// Where the PIF RAM is, as far as the command buffers passed in by the core show.
+///
+/// Plugins aren't told where the PIF RAM starts, only where each channel's buffer is. Every
+/// channel before a buffer takes up at least one byte of the PIF RAM, so it can't start later
+/// than the address of a buffer less its channel number. The earliest of these seen so far is
+/// taken as the start, which measures each buffer from the first one that starts right after
+/// the channels skipped before it. Padding in front of every buffer seen still makes a buffer
+/// look that much further from the end than it is.
+#[derive(Debug, Default)]
+pub struct PifRam {
+    start: Option<usize>,
+}
+
+impl PifRam {
+    /// The most bytes the buffer at an address for a channel can take up before the last byte of
+    /// the PIF RAM, which holds its status rather than commands.
+    pub fn max_buffer_len(&mut self, channel: usize, address: usize) -> usize {
+        let latest = address.saturating_sub(channel);
+        let start = self.start.map_or(latest, |start| start.min(latest));
+        self.start = Some(start);
+        (PIF_RAM_SIZE - 1).saturating_sub(address - start)
+    }
+}
+
+/// The command and response buffer for a single controller, as laid out by the core.
+///
+/// The first two bytes hold the transmit and receive lengths, followed by the command byte,
+/// its arguments and space for the response.
+pub struct PifCommand<'a> {
+    buffer: &'a mut [u8],
+}
+
+impl<'a> PifCommand<'a> {
+    /// Wrap a command buffer, or return `None` if its lengths don't fit.
+    pub fn new(buffer: &'a mut [u8]) -> Option<PifCommand<'a>> {
+        let tx = (*buffer.get(0)? & 0x3F) as usize;
+        let rx = (*buffer.get(1)? & 0x3F) as usize;
+        if tx == 0 || buffer.len() < 2 + tx + rx {
+            return None;
+        }
+        Some(PifCommand { buffer })
+    }
+
+    /// The length of the full buffer described by its first two bytes.
+    pub fn buffer_len(header: [u8; 2]) -> usize {
+        2 + (header[0] & 0x3F) as usize + (header[1] & 0x3F) as usize
+    }
+
+    fn tx(&self) -> usize {
+        (self.buffer[0] & 0x3F) as usize
+    }
+
+    fn rx(&self) -> usize {
+        (self.buffer[1] & 0x3F) as usize
+    }
+
+    /// The command byte.
+    pub fn command(&self) -> u8 {
+        self.buffer[2]
+    }
+
+    // the arguments following the command byte
+    fn args(&self) -> &[u8] {
+        &self.buffer[3..2 + self.tx()]
+    }
+
+    // the space for the response
+    fn response(&mut self) -> &mut [u8] {
+        let start = 2 + self.tx();
+        let end = start + self.rx();
+        &mut self.buffer[start..end]
+    }
+
+    /// Mark the command as unanswered, as if nothing were plugged in.
+    pub fn no_response(&mut self) {
+        self.buffer[1] |= NO_RESPONSE;
+    }
+
+    // the pak address in the arguments, and whether its checksum is valid
+    fn address(&self) -> Option<(u16, bool)> {
+        let args = self.args();
+        let address = u16::from_be_bytes([*args.get(0)?, *args.get(1)?]);
+        let block = address & !0x1F;
+        Some((block, address_crc(block) == (address & 0x1F) as u8))
+    }
+
+    /// Answer the command as a device with an optional pak.
+    ///
+    /// `poll` is only called when the console reads the buttons.
+    pub fn process<F>(
+        &mut self,
+        device: Device,
+        poll: F,
+        pak: Option<&mut (dyn PakDevice + 'static)>,
+    ) where
+        F: FnOnce() -> Inputs,
+    {
+        match self.command() {
+            STATUS | RESET if self.rx() >= 3 => {
+                let status = match device.has_pak_slot() {
+                    true if pak.is_some() => PAK_PRESENT,
+                    true => PAK_ABSENT,
+                    false => 0x00,
+                };
+                let response = self.response();
+                response[..2].copy_from_slice(&device.identity());
+                response[2] = status;
+            }
+            READ_BUTTONS if self.rx() >= 4 => {
+                let inputs = poll();
+                let value = inputs.to_value();
+                let (mut high, mut low) = ((value & 0xFF) as u8, ((value >> 8) & 0xFF) as u8);
+                if device == Device::Mouse {
+                    high &= MOUSE_BUTTONS;
+                    low = 0;
+                }
+                let response = self.response();
+                response[0] = high;
+                response[1] = low;
+                response[2] = inputs.x as u8;
+                response[3] = inputs.y as u8;
+            }
+            READ_PAK if self.rx() > BLOCK_SIZE && device.has_pak_slot() => {
+                let (address, valid) = match self.address() {
+                    Some(a) => a,
+                    None => return self.no_response(),
+                };
+                let mut block = [0; BLOCK_SIZE];
+                let crc = match pak {
+                    Some(pak) if valid => {
+                        pak.read(address, &mut block);
+                        data_crc(&block)
+                    }
+                    // the console treats a mismatched checksum as a missing or faulty pak
+                    _ => !data_crc(&block),
+                };
+                let response = self.response();
+                response[..BLOCK_SIZE].copy_from_slice(&block);
+                response[BLOCK_SIZE] = crc;
+            }
+            WRITE_PAK
+                if self.rx() >= 1
+                    && self.args().len() >= 2 + BLOCK_SIZE
+                    && device.has_pak_slot() =>
+            {
+                let (address, valid) = match self.address() {
+                    Some(a) => a,
+                    None => return self.no_response(),
+                };
+                let block: [u8; BLOCK_SIZE] = self.args()[2..2 + BLOCK_SIZE].try_into().unwrap();
+
+                let crc = match pak {
+                    Some(pak) if valid => {
+                        pak.write(address, &block);
+                        data_crc(&block)
+                    }
+                    _ => !data_crc(&block),
+                };
+                self.response()[0] = crc;
+            }
+            _ => self.no_response(),
+        }
+    }
+}
+
+#[test]
+fn address_crc_test() {
+    assert_eq!(address_crc(0x0000), 0x00);
+    assert_eq!(address_crc(0x8000), 0x01);
+    assert_eq!(address_crc(0xC000), 0x1B);
+}
+
+#[test]
+fn pak_roundtrip_test() {
+    let mut pak = crate::pak::MemPak::new();
+
+    let mut write = vec![35, 1, WRITE_PAK, 0x06, address_crc(0x0600)];
+    write.extend((0..BLOCK_SIZE as u8).map(|i| i * 3));
+    write.push(0);
+    PifCommand::new(&mut write).unwrap().process(
+        Device::Controller,
+        || Inputs::from_value(0),
+        Some(&mut pak),
+    );
+    let written: [u8; BLOCK_SIZE] = write[5..37].try_into().unwrap();
+    assert_eq!(write[37], data_crc(&written));
+
+    let mut read = vec![3, 33, READ_PAK, 0x06, address_crc(0x0600)];
+    read.extend([0; BLOCK_SIZE + 1].iter());
+    PifCommand::new(&mut read).unwrap().process(
+        Device::Controller,
+        || Inputs::from_value(0),
+        Some(&mut pak),
+    );
+    assert_eq!(&read[5..37], &write[5..37]);
+    assert_eq!(read[37], write[37]);
+}
+
+#[test]
+fn buffer_len_test() {
+    assert_eq!(PifCommand::buffer_len([1, 4]), 7);
+    assert_eq!(PifCommand::buffer_len([0xFF, 0xFF]), 128);
+
+    // with nothing else seen, the buffer is taken to start right after the skipped channels
+    let mut ram = PifRam::default();
+    assert_eq!(ram.max_buffer_len(1, 1001), 62);
+
+    // a later buffer is measured from the same start, and an earlier one moves it back
+    assert_eq!(ram.max_buffer_len(2, 1040), 23);
+    assert_eq!(ram.max_buffer_len(0, 990), 63);
+    assert_eq!(ram.max_buffer_len(2, 1040), 13);
+    assert_eq!(ram.max_buffer_len(3, 1060), 0);
+
+    // a buffer cut short at the end of the PIF RAM is rejected
+    let mut buffer = vec![35, 1, WRITE_PAK];
+    buffer.resize(ram.max_buffer_len(1, 1010), 0);
+    assert!(PifCommand::new(&mut buffer).is_some());
+    buffer.truncate(ram.max_buffer_len(1, 1030));
+    assert!(PifCommand::new(&mut buffer).is_none());
+}
+
+#[test]
+fn mouse_test() {
+    let mut status = vec![1, 3, STATUS, 0, 0, 0];
+    PifCommand::new(&mut status)
+        .unwrap()
+        .process(Device::Mouse, || unreachable!(), None);
+    assert_eq!(&status[3..], &[0x02, 0x00, 0x00]);
+
+    let mut inputs = Inputs::from_value(0);
+    inputs.a = true;
+    inputs.z = true;
+    inputs.x = -3;
+    inputs.y = 7;
+    let mut read = vec![1, 4, READ_BUTTONS, 0, 0, 0, 0];
+    PifCommand::new(&mut read)
+        .unwrap()
+        .process(Device::Mouse, || inputs, None);
+    assert_eq!(&read[3..], &[0x80, 0x00, 0xFD, 0x07]);
+}
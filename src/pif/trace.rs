@@ -0,0 +1,232 @@
+/*
+ * src/pif/trace.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{
+    address_crc, data_crc, NO_RESPONSE, PAK_ABSENT, PAK_PRESENT, READ_BUTTONS, READ_PAK, RESET,
+    STATUS, WRITE_PAK,
+};
+use crate::pak::BLOCK_SIZE;
+use std::{
+    convert::TryInto,
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// The point at which a command buffer was traced.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TraceStage {
+    /// The console sent the command, through `ControllerCommand`.
+    Command,
+    /// The console read the response, through `ReadController`.
+    Read,
+}
+
+/// A command buffer seen on a port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub frame: u64,
+    pub stage: TraceStage,
+    pub port: usize,
+    pub buffer: Vec<u8>,
+}
+
+impl TraceEntry {
+    /// Parse a line written by `PifTracer`.
+    pub fn parse(line: &str) -> Option<TraceEntry> {
+        let mut parts = line.split_whitespace();
+        let frame = parts.next()?.parse().ok()?;
+        let stage = match parts.next()? {
+            "C" => TraceStage::Command,
+            "R" => TraceStage::Read,
+            _ => return None,
+        };
+        let port = parts.next()?.parse::<usize>().ok()?.checked_sub(1)?;
+        let buffer = parts
+            .map(|b| u8::from_str_radix(b, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+
+        Some(TraceEntry {
+            frame,
+            stage,
+            port,
+            buffer,
+        })
+    }
+
+    // describe the command and its response
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (tx, rx) = match (self.buffer.get(0), self.buffer.get(1)) {
+            (Some(tx), Some(rx)) => ((tx & 0x3F) as usize, (rx & 0x3F) as usize),
+            _ => return write!(f, "truncated buffer"),
+        };
+        if self.buffer.len() < 2 + tx + rx || tx == 0 {
+            return write!(f, "malformed buffer, tx {} rx {}", tx, rx);
+        }
+
+        let args = &self.buffer[3..2 + tx];
+        let response = &self.buffer[2 + tx..2 + tx + rx];
+        let answered = self.stage == TraceStage::Read && self.buffer[1] & NO_RESPONSE == 0;
+        let address = || {
+            let address = u16::from_be_bytes([*args.get(0)?, *args.get(1)?]);
+            let block = address & !0x1F;
+            let valid = address_crc(block) == (address & 0x1F) as u8;
+            Some(format!(
+                "{:#06X} (address crc {})",
+                block,
+                if valid { "ok" } else { "bad" }
+            ))
+        };
+
+        match self.buffer[2] {
+            STATUS | RESET => {
+                write!(
+                    f,
+                    "{}",
+                    if self.buffer[2] == STATUS {
+                        "status"
+                    } else {
+                        "reset"
+                    }
+                )?;
+                if answered && rx >= 3 {
+                    let pak = match response[2] {
+                        PAK_PRESENT => "pak present",
+                        PAK_ABSENT => "no pak",
+                        _ => "no pak slot",
+                    };
+                    write!(f, " -> id {:02X}{:02X}, {}", response[0], response[1], pak)?;
+                }
+            }
+            READ_BUTTONS => {
+                write!(f, "read buttons")?;
+                if answered && rx >= 4 {
+                    write!(
+                        f,
+                        " -> buttons {:02X}{:02X}, x {}, y {}",
+                        response[0], response[1], response[2] as i8, response[3] as i8
+                    )?;
+                }
+            }
+            READ_PAK => {
+                write!(f, "read pak {}", address().unwrap_or_default())?;
+                if answered && rx > BLOCK_SIZE {
+                    let block: [u8; BLOCK_SIZE] = response[..BLOCK_SIZE].try_into().unwrap();
+                    let crc = response[BLOCK_SIZE];
+                    write!(
+                        f,
+                        " -> first byte {:02X}, data crc {:02X} ({})",
+                        block[0],
+                        crc,
+                        if crc == data_crc(&block) { "ok" } else { "bad" }
+                    )?;
+                }
+            }
+            WRITE_PAK => {
+                write!(f, "write pak {}", address().unwrap_or_default())?;
+                if let Some(value) = args.get(1 + BLOCK_SIZE) {
+                    write!(f, ", last byte {:02X}", value)?;
+                }
+                if answered && rx >= 1 {
+                    write!(f, " -> data crc {:02X}", response[0])?;
+                }
+            }
+            command => write!(f, "command {:02X}, tx {} rx {}", command, tx, rx)?,
+        }
+
+        if self.stage == TraceStage::Read && !answered {
+            write!(f, " -> no response")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stage = match self.stage {
+            TraceStage::Command => "sent",
+            TraceStage::Read => "read",
+        };
+        write!(f, "frame {} port {} {}: ", self.frame, self.port + 1, stage)?;
+        self.describe(f)
+    }
+}
+
+/// Writes every command buffer seen by the plugin to a file.
+///
+/// Each line holds the frame, `C` or `R` for the stage, the port and the buffer in hex.
+pub struct PifTracer {
+    writer: BufWriter<File>,
+}
+
+impl PifTracer {
+    /// Start a trace in a file, replacing anything already in it.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<PifTracer> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        Ok(PifTracer {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    /// Record a command buffer.
+    pub fn record(
+        &mut self,
+        frame: u64,
+        stage: TraceStage,
+        port: usize,
+        buffer: &[u8],
+    ) -> io::Result<()> {
+        let stage = match stage {
+            TraceStage::Command => 'C',
+            TraceStage::Read => 'R',
+        };
+        write!(self.writer, "{} {} {}", frame, stage, port + 1)?;
+        for byte in buffer {
+            write!(self.writer, " {:02X}", byte)?;
+        }
+        writeln!(self.writer)
+    }
+
+    /// Write any buffered lines to the file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[test]
+fn trace_decode_test() {
+    let line = format!(
+        "12 R 2 23 01 03 C0 {:02X} {}",
+        0x1B,
+        "01 ".repeat(32) + "EB"
+    );
+    let entry = TraceEntry::parse(&line).unwrap();
+    assert_eq!(entry.frame, 12);
+    assert_eq!(entry.port, 1);
+    assert_eq!(
+        entry.to_string(),
+        "frame 12 port 2 read: write pak 0xC000 (address crc ok), last byte 01 -> data crc EB"
+    );
+}
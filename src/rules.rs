@@ -0,0 +1,240 @@
+/*
+ * src/rules.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::{ClampMode, Directional, Inputs, StickOrigin};
+
+/// A check made on the inputs of every frame sent to the emulator.
+///
+/// These catch inputs that a real controller cannot produce, which movie submission sites
+/// reject.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Rule {
+    /// Up and down, or left and right, held together on the D-pad.
+    OppositeDpad,
+    /// Up and down, or left and right, held together on the C buttons.
+    OppositeC,
+    /// The stick outside of the octagonal gate of the controller.
+    StickGate,
+    /// L, R and Start held together, which a controller reports as a recalibration.
+    ResetCombo,
+}
+
+impl Rule {
+    /// Every rule, in the order they are checked.
+    pub const ALL: [Rule; 4] = [
+        Rule::OppositeDpad,
+        Rule::OppositeC,
+        Rule::StickGate,
+        Rule::ResetCombo,
+    ];
+
+    /// The name shown to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::OppositeDpad => "Opposite D-pad directions",
+            Rule::OppositeC => "Opposite C directions",
+            Rule::StickGate => "Stick outside the gate",
+            Rule::ResetCombo => "L + R + Start",
+        }
+    }
+
+    /// The parameter holding the action for this rule in the configuration file.
+    pub fn param(self) -> &'static str {
+        match self {
+            Rule::OppositeDpad => "RuleOppositeDpad",
+            Rule::OppositeC => "RuleOppositeC",
+            Rule::StickGate => "RuleStickGate",
+            Rule::ResetCombo => "RuleResetCombo",
+        }
+    }
+
+    /// Tell whether a set of inputs breaks this rule.
+    pub fn is_broken(self, inputs: &Inputs) -> bool {
+        match self {
+            Rule::OppositeDpad => has_opposites(&inputs.d),
+            Rule::OppositeC => has_opposites(&inputs.c),
+            Rule::StickGate => !ClampMode::Gate.contains(inputs.x, inputs.y),
+            Rule::ResetCombo => StickOrigin::is_combo(inputs),
+        }
+    }
+
+    /// Change a set of inputs so they no longer break this rule.
+    pub fn fix(self, inputs: &mut Inputs) {
+        match self {
+            Rule::OppositeDpad => release_opposites(&mut inputs.d),
+            Rule::OppositeC => release_opposites(&mut inputs.c),
+            Rule::StickGate => {
+                let (x, y) = ClampMode::Gate.clamp(inputs.x, inputs.y);
+                inputs.x = x;
+                inputs.y = y;
+            }
+            Rule::ResetCombo => inputs.start = false,
+        }
+    }
+}
+
+fn has_opposites(directional: &Directional) -> bool {
+    (directional.up && directional.down) || (directional.left && directional.right)
+}
+
+// neither direction wins, so both are released
+fn release_opposites(directional: &mut Directional) {
+    if directional.up && directional.down {
+        directional.up = false;
+        directional.down = false;
+    }
+    if directional.left && directional.right {
+        directional.left = false;
+        directional.right = false;
+    }
+}
+
+/// What happens to a frame that breaks a rule.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RuleAction {
+    /// The inputs are sent as they are.
+    Allow,
+    /// The inputs are sent as they are, but the broken rule is reported.
+    Warn,
+    /// The offending inputs are removed before they are sent, and the broken rule is reported.
+    Block,
+}
+
+impl RuleAction {
+    /// Every action, in the order of their configuration values.
+    pub const ALL: [RuleAction; 3] = [RuleAction::Allow, RuleAction::Warn, RuleAction::Block];
+
+    /// The name shown to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            RuleAction::Allow => "Allow",
+            RuleAction::Warn => "Warn",
+            RuleAction::Block => "Block",
+        }
+    }
+
+    /// Convert the value stored in the configuration file to an action.
+    pub fn from_int(value: i32) -> RuleAction {
+        match value {
+            0 => RuleAction::Allow,
+            2 => RuleAction::Block,
+            _ => RuleAction::Warn,
+        }
+    }
+
+    /// Convert this action to the value stored in the configuration file.
+    pub fn to_int(self) -> i32 {
+        match self {
+            RuleAction::Allow => 0,
+            RuleAction::Warn => 1,
+            RuleAction::Block => 2,
+        }
+    }
+}
+
+/// The action chosen for each rule.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rules {
+    actions: [RuleAction; 4],
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            actions: [RuleAction::Warn; 4],
+        }
+    }
+}
+
+impl Rules {
+    /// The action taken when a rule is broken.
+    pub fn action(&self, rule: Rule) -> RuleAction {
+        self.actions[rule as usize]
+    }
+
+    /// Change the action taken when a rule is broken.
+    pub fn set_action(&mut self, rule: Rule, action: RuleAction) {
+        self.actions[rule as usize] = action;
+    }
+
+    /// Check a frame of inputs against every rule without changing it.
+    ///
+    /// Returns the rules that were broken and not allowed.
+    pub fn check(&self, inputs: &Inputs) -> Vec<Rule> {
+        Rule::ALL
+            .iter()
+            .copied()
+            .filter(|rule| self.action(*rule) != RuleAction::Allow && rule.is_broken(inputs))
+            .collect()
+    }
+
+    /// Check a frame of inputs against every rule, fixing the ones that are blocked.
+    ///
+    /// Returns the rules that were broken and not allowed.
+    pub fn apply(&self, inputs: &mut Inputs) -> Vec<Rule> {
+        let mut broken = Vec::new();
+        for rule in Rule::ALL.iter().copied() {
+            let action = self.action(rule);
+            if action == RuleAction::Allow || !rule.is_broken(inputs) {
+                continue;
+            }
+            if action == RuleAction::Block {
+                rule.fix(inputs);
+            }
+            broken.push(rule);
+        }
+        broken
+    }
+}
+
+#[test]
+fn rules_test() {
+    let mut inputs = Inputs::from_value(0);
+    inputs.d.left = true;
+    inputs.d.right = true;
+    inputs.c.up = true;
+    inputs.x = 127;
+    inputs.l = true;
+    inputs.r = true;
+    inputs.start = true;
+
+    let mut rules = Rules::default();
+    rules.set_action(Rule::ResetCombo, RuleAction::Allow);
+    rules.set_action(Rule::StickGate, RuleAction::Block);
+    assert_eq!(
+        rules.check(&inputs),
+        vec![Rule::OppositeDpad, Rule::StickGate]
+    );
+    let broken = rules.apply(&mut inputs);
+    assert_eq!(broken, vec![Rule::OppositeDpad, Rule::StickGate]);
+
+    // warnings leave the inputs alone
+    assert!(inputs.d.left && inputs.d.right && inputs.c.up && inputs.start);
+    assert_eq!((inputs.x, inputs.y), (85, 0));
+
+    rules.set_action(Rule::OppositeDpad, RuleAction::Block);
+    rules.set_action(Rule::ResetCombo, RuleAction::Block);
+    assert_eq!(
+        rules.apply(&mut inputs),
+        vec![Rule::OppositeDpad, Rule::ResetCombo]
+    );
+    assert!(!inputs.d.left && !inputs.d.right && !inputs.start);
+    assert!(rules.apply(&mut inputs).is_empty());
+}
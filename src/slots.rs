@@ -0,0 +1,126 @@
+/*
+ * src/slots.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Numbered slots holding complete snapshots of the inputs.
+
+use crate::Inputs;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The number of slots available.
+pub const SLOT_COUNT: usize = 10;
+
+/// A set of numbered input snapshots, optionally backed by a file.
+#[derive(Debug, Clone, Default)]
+pub struct InputSlots {
+    slots: [Option<Inputs>; SLOT_COUNT],
+    path: Option<PathBuf>,
+}
+
+impl InputSlots {
+    /// Create a set of empty slots that is only kept in memory.
+    pub fn new() -> InputSlots {
+        InputSlots::default()
+    }
+
+    /// Load the slots from a file, which is also used for saving them later on.
+    ///
+    /// A file that does not exist yet results in empty slots.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<InputSlots> {
+        let path = path.as_ref();
+        let mut slots = InputSlots {
+            slots: Default::default(),
+            path: Some(path.to_path_buf()),
+        };
+
+        let contents = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(slots),
+            Err(e) => return Err(e),
+        };
+
+        // each line consists of the slot index and the inputs as a hex value
+        for line in contents.lines() {
+            let mut parts = line.split_whitespace();
+            let (index, value) = match (parts.next(), parts.next()) {
+                (Some(i), Some(v)) => (i, v),
+                _ => continue,
+            };
+
+            let invalid = || io::Error::new(io::ErrorKind::InvalidData, line.to_string());
+            let index: usize = index.parse().map_err(|_| invalid())?;
+            let value = u32::from_str_radix(value, 16).map_err(|_| invalid())?;
+            if index < SLOT_COUNT {
+                slots.slots[index] = Some(Inputs::from_value(value));
+            }
+        }
+
+        Ok(slots)
+    }
+
+    /// Write the slots to the file they were loaded from, if any.
+    pub fn save(&self) -> io::Result<()> {
+        let path = match self.path {
+            Some(ref p) => p,
+            None => return Ok(()),
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let contents: String = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| s.map(|s| format!("{} {:08X}\n", i, s.to_value())))
+            .collect();
+        fs::write(path, contents)
+    }
+
+    /// Get the inputs stored in a slot.
+    pub fn get(&self, index: usize) -> Option<Inputs> {
+        self.slots.get(index).copied().flatten()
+    }
+
+    /// Store inputs into a slot.
+    pub fn set(&mut self, index: usize, inputs: Option<Inputs>) {
+        if let Some(slot) = self.slots.get_mut(index) {
+            *slot = inputs;
+        }
+    }
+}
+
+#[test]
+fn slot_roundtrip_test() {
+    let path = std::env::temp_dir().join(format!("tasinput2-slots-{}", std::process::id()));
+    let mut slots = InputSlots::load(&path).unwrap();
+    assert!(slots.get(3).is_none());
+
+    slots.set(3, Some(Inputs::from_value(0x7F81_2080)));
+    slots.save().unwrap();
+
+    let slots = InputSlots::load(&path).unwrap();
+    assert_eq!(slots.get(3).unwrap().to_value(), 0x7F81_2080);
+    assert!(slots.get(4).is_none());
+    fs::remove_file(&path).unwrap();
+}
@@ -41,4 +41,6 @@ pub enum StateError {
     ThreadJoinPanic,
     #[error("Difficulties converting string to CString")]
     String(#[from] NulError),
+    #[error("The emulator core returned error code {0}")]
+    Core(m64p_sys::m64p_error),
 }
@@ -20,23 +20,54 @@
 
 mod error;
 mod qt_thread;
+mod shared;
 
-use crate::{Inputs, CONTROLLER_COUNT};
+use crate::{
+    config::{ConfigureFunctions, Settings},
+    core_lib::Core,
+    movie::{
+        Branches, Greenzone, MovieEvent, MovieHeader, MovieState, PortHeader, RecordMode,
+        TimedEvent,
+    },
+    pak::{GbCart, MemPak, Pak, PakDevice, RumblePak, TransferPak},
+    pif::{PifCommand, PifRam, PifTracer, TraceStage},
+    ClampMode, Device, InputSlots, Inputs, PatternGenerator, Ramp, Rule, StickOrigin, StickPattern,
+    CONTROLLER_COUNT,
+};
 use qt_widgets::qt_core::QCoreApplication;
 use std::{
-    sync::{Arc, Mutex},
+    collections::VecDeque,
+    convert::TryInto,
+    os::raw::{c_int, c_uint},
+    path::{Path, PathBuf},
     thread::{self, JoinHandle},
 };
 
 pub use error::StateError;
+pub use shared::SharedState;
+
+// the controller entries the core reads the setup of each port from
+struct Controls(*mut m64p_sys::CONTROL);
+
+// the core keeps the entries alive for as long as the plugin is attached
+unsafe impl Send for Controls {}
 
 /// Represents the current state of the program as a whole
 pub struct Tasinput2State {
     pub is_initialized: bool,
     pub is_rom_open: bool,
     pub is_gui_open: bool,
-    inputs: Arc<[Arc<Mutex<Inputs>>; CONTROLLER_COUNT]>,
+    pub core: Option<Core>,
+    pub config: Option<ConfigureFunctions>,
+    pub settings: Settings,
+    shared: SharedState,
     qt_thread: Option<JoinHandle<()>>,
+    pak_devices: [Option<Box<dyn PakDevice>>; CONTROLLER_COUNT],
+    tracer: Option<PifTracer>,
+    pif_ram: PifRam,
+    controls: Option<Controls>,
+    ports: [PortHeader; CONTROLLER_COUNT],
+    frame_callback: bool,
 }
 
 impl Tasinput2State {
@@ -46,29 +77,214 @@ impl Tasinput2State {
             is_initialized: false,
             is_rom_open: false,
             is_gui_open: false,
-            inputs: Arc::new(array_init::array_init(|_| {
-                Arc::new(Mutex::new(Inputs::from_value(0)))
-            })),
+            core: None,
+            config: None,
+            settings: Settings::default(),
+            shared: SharedState::new(),
             qt_thread: None,
+            pak_devices: Default::default(),
+            tracer: None,
+            pif_ram: PifRam::default(),
+            controls: None,
+            ports: [PortHeader::default(); CONTROLLER_COUNT],
+            frame_callback: false,
+        }
+    }
+
+    /// Reload the settings from the configuration file.
+    pub fn load_settings(&mut self) -> Result<(), StateError> {
+        if let Some(ref config) = self.config {
+            self.settings = Settings::load(config)?;
+        }
+        Ok(())
+    }
+
+    /// Plug the configured controllers and paks into the core's controller entries.
+    ///
+    /// # Safety
+    ///
+    /// `controls` must point to an entry for every port, which stays valid for as long as the
+    /// plugin is attached.
+    pub unsafe fn initiate_controllers(&mut self, controls: *mut m64p_sys::CONTROL) {
+        self.controls = Some(Controls(controls));
+        for control in 0..CONTROLLER_COUNT {
+            self.ports[control] = PortHeader {
+                present: self.settings.controllers[control],
+                pak: self.settings.paks[control],
+            };
+            self.write_control(control);
+        }
+        *self.shared.origins.lock().unwrap() = Default::default();
+    }
+
+    // write the setup of a port to the core's controller entry
+    fn write_control(&mut self, control: usize) {
+        if let Some(Controls(controls)) = self.controls {
+            let port = self.ports[control];
+            let entry = unsafe { &mut *controls.add(control) };
+            entry.Present = port.present as c_int;
+            entry.Plugin = port.pak.to_plugin();
+            entry.RawData = self.settings.raw_data as c_int;
+        }
+    }
+
+    /// Apply any controllers or paks plugged in or pulled out from the GUI, before a port is
+    /// polled.
+    ///
+    /// The ports recorded in the header of a movie that was just opened or played from the
+    /// start are plugged in first, and a movie being played swaps them as its events say. While
+    /// recording, each change is recorded in the movie as an event on the frame the port is
+    /// polled for.
+    pub fn sync_ports(&mut self, control: usize) {
+        if std::mem::take(&mut *self.shared.header_pending.lock().unwrap()) {
+            let header = self.shared.movie.lock().unwrap().header.clone();
+            if let Err(e) = self.apply_movie_header(&header) {
+                dprintln!("Unable to save the ports from the movie header: {}", e);
+            }
+        }
+
+        let frame = if self.first_port(control) {
+            self.frame()
+        } else {
+            self.frame().saturating_sub(1)
+        };
+        let movie_state = *self.shared.movie_state.lock().unwrap();
+        if movie_state == MovieState::Playing {
+            self.play_port_events(frame);
+        }
+
+        let plugged = *self.shared.plugged.lock().unwrap();
+        let paks = *self.shared.paks.lock().unwrap();
+
+        for control in 0..CONTROLLER_COUNT {
+            let port = self.ports[control];
+            if port.present == plugged[control] && port.pak == paks[control] {
+                continue;
+            }
+
+            if port.present != plugged[control] {
+                dprintln!(
+                    "Frame {}: port {} {}",
+                    frame,
+                    control + 1,
+                    if plugged[control] {
+                        "plugged in"
+                    } else {
+                        "unplugged"
+                    }
+                );
+                self.record_event(
+                    movie_state,
+                    TimedEvent {
+                        frame,
+                        event: MovieEvent::Plug {
+                            port: control,
+                            present: plugged[control],
+                        },
+                    },
+                );
+            }
+            self.ports[control].present = plugged[control];
+            if plugged[control] {
+                // a controller takes its stick position as the origin when plugged in
+                self.shared.origins.lock().unwrap()[control] = StickOrigin::default();
+            }
+
+            if port.pak != paks[control] {
+                dprintln!(
+                    "Frame {}: {} inserted into port {}",
+                    frame,
+                    paks[control].name(),
+                    control + 1
+                );
+                self.record_event(
+                    movie_state,
+                    TimedEvent {
+                        frame,
+                        event: MovieEvent::Pak {
+                            port: control,
+                            pak: paks[control],
+                        },
+                    },
+                );
+                self.ports[control].pak = paks[control];
+                self.close_pak(control);
+                self.open_pak(control);
+            }
+
+            self.write_control(control);
+        }
+    }
+
+    // plug in the controllers and paks the movie being played swaps on a frame
+    fn play_port_events(&self, frame: u64) {
+        let movie = self.shared.movie.lock().unwrap();
+        for timed in movie.events.at(frame) {
+            match timed.event {
+                MovieEvent::Plug { port, present } => {
+                    self.shared.plugged.lock().unwrap()[port] = present
+                }
+                MovieEvent::Pak { port, pak } => self.shared.paks.lock().unwrap()[port] = pak,
+                MovieEvent::Recalibrate { .. } => (),
+            }
+        }
+    }
+
+    // add an event to the movie if it is being recorded, undone with the rest of the recording
+    fn record_event(&self, movie_state: MovieState, event: TimedEvent) {
+        if let MovieState::Recording(group) = movie_state {
+            self.shared.add_event(group, event);
         }
     }
 
     /// Initialize the QT thread
-    pub fn start_qt(&mut self, controllers: u8) -> Result<(), StateError> {
+    pub fn start_qt(&mut self) -> Result<(), StateError> {
         if !(unsafe { QCoreApplication::instance().is_null() }) || self.is_gui_open {
             return Err(StateError::QtOpen);
         }
 
-        let controllers = [
-            controllers & 1 != 0,
-            controllers & 2 != 0,
-            controllers & 4 != 0,
-            controllers & 8 != 0,
-        ];
+        // load the input slots saved for this ROM
+        if let Some(path) = self.slots_path() {
+            match InputSlots::load(&path) {
+                Ok(slots) => *self.shared.slots.lock().unwrap() = slots,
+                Err(e) => dprintln!("Unable to load input slots: {}", e),
+            }
+        }
+
+        // and the branches made for it
+        if let Some(path) = self.branches_path() {
+            match Branches::load(&path) {
+                Ok(branches) => *self.shared.branches.lock().unwrap() = branches,
+                Err(e) => dprintln!("Unable to load branches: {}", e),
+            }
+        }
+
+        // the greenzone starts out empty, its states from last time may not match the movie
+        if let Some(path) = self.rom_data_path("greenzone", "") {
+            *self.shared.greenzone.lock().unwrap() = Greenzone::open(
+                &path,
+                self.settings.greenzone_interval,
+                self.settings.greenzone_states,
+            );
+        }
+
+        *self.shared.paks.lock().unwrap() = self.settings.paks;
+        *self.shared.plugged.lock().unwrap() = self.settings.controllers;
+        *self.shared.record_mode.lock().unwrap() = self.settings.record_mode;
+
+        // a new movie is recorded with the game and controllers as they are set up now
+        let mut movie = self.shared.movie.lock().unwrap();
+        if movie.is_empty() {
+            movie.header = self.movie_header();
+        }
+        drop(movie);
 
-        let inputs_cloned = self.inputs.clone();
+        let settings_cloned = self.settings.clone();
+        let config_cloned = self.config.clone();
+        let core_cloned = self.core.clone();
+        let shared_cloned = self.shared.clone();
         self.qt_thread = Some(thread::spawn(move || unsafe {
-            qt_thread::qt_thread(controllers, inputs_cloned);
+            qt_thread::qt_thread(settings_cloned, config_cloned, core_cloned, shared_cloned);
         }));
 
         self.is_gui_open = true;
@@ -96,10 +312,543 @@ impl Tasinput2State {
         }
     }
 
+    // a file in the data directory named after the current ROM
+    fn rom_data_path(&self, directory: &str, suffix: &str) -> Option<PathBuf> {
+        let data_directory = self.config.as_ref()?.data_directory()?;
+        let rom = match self.core.as_ref()?.rom_info() {
+            Ok(rom) => rom,
+            Err(e) => {
+                dprintln!("Unable to get ROM information: {}", e);
+                return None;
+            }
+        };
+
+        Some(
+            data_directory
+                .join(directory)
+                .join(format!("{}{}", rom.file_stem(), suffix)),
+        )
+    }
+
+    /// The file where the input slots for the current ROM are kept.
+    pub fn slots_path(&self) -> Option<PathBuf> {
+        self.rom_data_path("slots", ".txt")
+    }
+
+    /// The directory where the branches for the current ROM are kept.
+    pub fn branches_path(&self) -> Option<PathBuf> {
+        self.rom_data_path("branches", "")
+    }
+
+    /// The file backing the Controller Pak in a port for the current ROM.
+    pub fn mempak_path(&self, control: usize) -> Option<PathBuf> {
+        self.rom_data_path("mempaks", &format!("-{}.mpk", control + 1))
+    }
+
+    /// Plug in the paks the plugin emulates when answering controller commands itself.
+    pub fn open_paks(&mut self) {
+        for control in 0..CONTROLLER_COUNT {
+            self.open_pak(control);
+        }
+    }
+
+    // plug in the emulated pak for a port
+    fn open_pak(&mut self, control: usize) {
+        if !self.settings.raw_data || !self.settings.devices[control].has_pak_slot() {
+            return;
+        }
+
+        let device: Option<Box<dyn PakDevice>> = match self.ports[control].pak {
+            Pak::MemPak => Some(Box::new(match self.mempak_path(control) {
+                Some(path) => MemPak::load(&path).unwrap_or_else(|e| {
+                    dprintln!("Unable to load Controller Pak: {}", e);
+                    MemPak::new()
+                }),
+                None => MemPak::new(),
+            })),
+            Pak::RumblePak => Some(Box::new(RumblePak::new(
+                self.shared.rumble[control].clone(),
+            ))),
+            Pak::TransferPak => Some(Box::new(TransferPak::new(self.load_gb_cart(control)))),
+            Pak::None | Pak::Raw => None,
+        };
+        self.pak_devices[control] = device;
+    }
+
+    // load the cartridge for the transfer pak in a port
+    fn load_gb_cart(&self, control: usize) -> Option<GbCart> {
+        let rom = &self.settings.gb_roms[control];
+        if rom.is_empty() {
+            dprintln!(
+                "No Game Boy ROM set for the transfer pak in port {}",
+                control + 1
+            );
+            return None;
+        }
+
+        let save = match self.settings.gb_saves[control] {
+            ref s if s.is_empty() => Path::new(rom).with_extension("sav"),
+            ref s => PathBuf::from(s),
+        };
+        match GbCart::load(rom, &save) {
+            Ok(cart) => Some(cart),
+            Err(e) => {
+                dprintln!("Unable to load Game Boy cartridge {}: {}", rom, e);
+                None
+            }
+        }
+    }
+
+    /// Save and unplug the emulated paks.
+    pub fn close_paks(&mut self) {
+        // the next game starts from the first frame, with the movie stopped
+        *self.shared.frame.lock().unwrap() = 0;
+        *self.shared.movie_state.lock().unwrap() = MovieState::Stopped;
+        *self.shared.seek.lock().unwrap() = None;
+        for control in 0..CONTROLLER_COUNT {
+            self.close_pak(control);
+        }
+    }
+
+    // save and unplug the emulated pak for a port
+    fn close_pak(&mut self, control: usize) {
+        if let Some(mut device) = self.pak_devices[control].take() {
+            if let Err(e) = device.flush() {
+                dprintln!("Unable to save pak: {}", e);
+            }
+        }
+        *self.shared.rumble[control].lock().unwrap() = false;
+    }
+
+    /// The most bytes the command buffer at an address for a controller can take up.
+    pub fn max_buffer_len(&mut self, control: usize, address: usize) -> usize {
+        self.pif_ram.max_buffer_len(control, address)
+    }
+
+    // the first port that is plugged in is polled once per frame and moves on to the next one,
+    // which the other ports have to look back from
+    fn first_port(&self, control: usize) -> bool {
+        self.ports.iter().position(|p| p.present) == Some(control)
+    }
+
+    /// Answer a command sent by the console to a controller.
+    pub fn raw_command(&mut self, control: usize, buffer: &mut [u8]) {
+        self.sync_ports(control);
+        // this replaces any frame callback the front-end set, for good
+        self.update_frame_callback();
+        let frame = self.frame();
+        let rumble = self.rumble(control);
+
+        let mut device = self.pak_devices[control].take();
+        if let Some(mut command) = PifCommand::new(buffer) {
+            if self.ports[control].present {
+                command.process(
+                    self.settings.devices[control],
+                    || self.poll_inputs(control),
+                    device.as_deref_mut(),
+                );
+            } else {
+                command.no_response();
+            }
+        }
+        self.pak_devices[control] = device;
+        self.trace(frame, TraceStage::Read, control, buffer);
+
+        if self.rumble(control) != rumble {
+            dprintln!(
+                "Frame {}: rumble on port {} turned {}",
+                frame,
+                control + 1,
+                if rumble { "off" } else { "on" }
+            );
+        }
+    }
+
+    /// Start tracing controller commands, if enabled in the settings.
+    pub fn open_trace(&mut self) {
+        if !self.settings.trace_pif {
+            return;
+        }
+
+        if let Some(path) = self.rom_data_path("traces", ".pif.txt") {
+            match PifTracer::create(&path) {
+                Ok(tracer) => self.tracer = Some(tracer),
+                Err(e) => dprintln!("Unable to create PIF trace: {}", e),
+            }
+        }
+    }
+
+    /// Stop tracing controller commands.
+    pub fn close_trace(&mut self) {
+        if let Some(mut tracer) = self.tracer.take() {
+            if let Err(e) = tracer.flush() {
+                dprintln!("Unable to write PIF trace: {}", e);
+            }
+        }
+    }
+
+    /// Record a command buffer in the trace, if one is open.
+    pub fn trace(&mut self, frame: u64, stage: TraceStage, control: usize, buffer: &[u8]) {
+        if let Some(ref mut tracer) = self.tracer {
+            if let Err(e) = tracer.record(frame, stage, control, buffer) {
+                dprintln!("Unable to write PIF trace: {}", e);
+                self.tracer = None;
+            }
+        }
+    }
+
+    /// Whether the rumble pak in a port is currently rumbling.
+    pub fn rumble(&self, control: usize) -> bool {
+        *self.shared.rumble[control].lock().unwrap()
+    }
+
+    /// The number of frames of input sent to the emulator so far.
+    pub fn frame(&self) -> u64 {
+        *self.shared.frame.lock().unwrap()
+    }
+
+    /// A movie header describing the controllers currently plugged in.
+    pub fn movie_header(&self) -> MovieHeader {
+        let mut header = MovieHeader::default();
+        header.ports = self.ports;
+
+        if let Some(rom) = self.core.as_ref().and_then(|core| core.rom_info().ok()) {
+            header.rom_name = rom.name;
+            header.rom_crc = rom.crc1;
+            header.rom_country = rom.country;
+        }
+        header.input_plugin = format!("tasinput2 {}", env!("CARGO_PKG_VERSION"));
+        header
+    }
+
+    /// Plug in the controllers and paks recorded in a movie header.
+    ///
+    /// The ports are switched over right away without recording any events, and the setup is
+    /// saved to the configuration file for the next time the game is started.
+    pub fn apply_movie_header(&mut self, header: &MovieHeader) -> Result<(), StateError> {
+        for (control, port) in header.ports.iter().enumerate() {
+            self.settings.controllers[control] = port.present;
+            self.settings.paks[control] = port.pak;
+
+            self.close_pak(control);
+            self.ports[control] = *port;
+            self.open_pak(control);
+            self.write_control(control);
+        }
+        *self.shared.paks.lock().unwrap() = self.settings.paks;
+        *self.shared.plugged.lock().unwrap() = self.settings.controllers;
+
+        if let Some(ref config) = self.config {
+            self.settings.save(config)?;
+        }
+        Ok(())
+    }
+
     /// Get the inputs for a specific controller.
     pub fn get_inputs(&self, control: usize) -> Inputs {
-        *self.inputs[control].lock().unwrap()
+        *self.shared.inputs[control].lock().unwrap()
+    }
+
+    /// Get the inputs to send to the emulator for the current frame.
+    ///
+    /// A movie being played back takes priority over everything else, as do ports that are not
+    /// recorded while the movie is recorded over. Otherwise queued frames take priority over the
+    /// inputs set in the GUI, which are in turn driven by any active stick pattern. The stick is
+    /// then shifted by the controller's origin, if recalibration is emulated, and restricted by
+    /// the current clamp mode. When recording, the result is written into the movie according to
+    /// the record mode. Every frame is checked against the rules, but frames played back from the
+    /// movie are only reported, not fixed.
+    pub fn poll_inputs(&mut self, control: usize) -> Inputs {
+        let first_port = self.first_port(control);
+        let frame = if first_port {
+            let mut frame = self.shared.frame.lock().unwrap();
+            *frame += 1;
+            *frame - 1
+        } else {
+            self.frame().saturating_sub(1)
+        };
+
+        let movie_state = *self.shared.movie_state.lock().unwrap();
+        let mode = *self.shared.record_mode.lock().unwrap();
+        match movie_state {
+            MovieState::Playing => {
+                self.play_recalibration(frame, control);
+                let movie = self.shared.movie.lock().unwrap();
+                if let Some(recorded) = movie.frame(frame as usize).map(|f| f[control]) {
+                    drop(movie);
+                    return self.check_played(frame, control, recorded);
+                }
+                drop(movie);
+                dprintln!("Frame {}: movie ended", frame);
+                *self.shared.movie_state.lock().unwrap() = MovieState::Stopped;
+            }
+            MovieState::Recording(group) => {
+                // the first port makes room for the frame, which every port then records into
+                if first_port && mode == RecordMode::Insert {
+                    self.shared.edit_frame(group, frame as usize, true, |_| ());
+                }
+
+                // ports that are not recorded keep playing back, past the end as well
+                if !self.shared.recorded_ports.lock().unwrap()[control] {
+                    self.play_recalibration(frame, control);
+                    let movie = self.shared.movie.lock().unwrap();
+                    let recorded = match movie.frame(frame as usize) {
+                        Some(recorded) => recorded[control],
+                        None => Inputs::from_value(0),
+                    };
+                    drop(movie);
+                    return self.check_played(frame, control, recorded);
+                }
+            }
+            MovieState::Stopped => (),
+        }
+
+        let queued = self.shared.queues[control].lock().unwrap().pop_front();
+        let mut inputs = match queued {
+            Some(inputs) => inputs,
+            None => {
+                let mut inputs = self.get_inputs(control);
+                if let Some(ref mut generator) = *self.shared.patterns[control].lock().unwrap() {
+                    generator.apply(&mut inputs);
+                }
+                inputs
+            }
+        };
+
+        // mouse motion is not limited like a stick
+        if self.settings.devices[control] == Device::Controller {
+            if self.settings.recalibration {
+                let mut origin = self.shared.origins.lock().unwrap()[control];
+                if origin.apply(&mut inputs) {
+                    dprintln!(
+                        "Frame {}: port {} recalibrated to {}, {}",
+                        frame,
+                        control + 1,
+                        origin.x,
+                        origin.y
+                    );
+                    self.record_event(
+                        movie_state,
+                        TimedEvent {
+                            frame,
+                            event: MovieEvent::Recalibrate {
+                                port: control,
+                                x: origin.x,
+                                y: origin.y,
+                            },
+                        },
+                    );
+                }
+                self.shared.origins.lock().unwrap()[control] = origin;
+            }
+
+            let (x, y) = self.clamp().clamp(inputs.x, inputs.y);
+            inputs.x = x;
+            inputs.y = y;
+
+            // checked last, on exactly what the emulator will see
+            let broken = self.settings.rules.apply(&mut inputs);
+            self.report_rules(frame, control, broken);
+        }
+
+        if let MovieState::Recording(group) = movie_state {
+            self.shared
+                .edit_frame(group, frame as usize, false, |recorded| {
+                    recorded[control] = mode.combine(recorded[control], inputs);
+                    inputs = recorded[control];
+                });
+        }
+        inputs
+    }
+
+    // report the rules broken by a frame played back from the movie, which is sent as it is
+    fn check_played(&self, frame: u64, control: usize, inputs: Inputs) -> Inputs {
+        if self.settings.devices[control] == Device::Controller {
+            let broken = self.settings.rules.check(&inputs);
+            self.report_rules(frame, control, broken);
+        }
+        inputs
+    }
+
+    // log the rules broken by a frame sent from a port, and show them in its controller window
+    fn report_rules(&self, frame: u64, control: usize, broken: Vec<Rule>) {
+        for rule in broken.iter() {
+            let action = self.settings.rules.action(*rule);
+            dprintln!(
+                "Frame {}: port {} broke rule \"{}\" ({})",
+                frame,
+                control + 1,
+                rule.name(),
+                action.name().to_lowercase()
+            );
+        }
+        self.shared.broken_rules.lock().unwrap()[control] = broken;
+    }
+
+    // move the stick origin of a port where the movie being played recalibrated it, which the
+    // recorded frames are already relative to
+    fn play_recalibration(&self, frame: u64, control: usize) {
+        let movie = self.shared.movie.lock().unwrap();
+        for timed in movie.events.at(frame) {
+            if let MovieEvent::Recalibrate { port, x, y } = timed.event {
+                if port == control {
+                    self.shared.origins.lock().unwrap()[control] = StickOrigin { x, y };
+                }
+            }
+        }
+    }
+
+    /// Take over the core's frame callback while the movie is played or recorded, a seek is
+    /// running or a breakpoint is set, and give it up otherwise.
+    ///
+    /// The core only keeps one frame callback and has no way to read it back, so one set by the
+    /// front-end is replaced while this one is in place, and is not restored afterwards.
+    pub fn update_frame_callback(&mut self) {
+        let wanted = *self.shared.movie_state.lock().unwrap() != MovieState::Stopped
+            || self.shared.seek.lock().unwrap().is_some()
+            || !self.shared.breakpoints.lock().unwrap().list().is_empty();
+        self.set_frame_callback(wanted);
+    }
+
+    /// Set or clear the frame callback, unless it already is.
+    pub fn set_frame_callback(&mut self, installed: bool) {
+        if installed == self.frame_callback {
+            return;
+        }
+        let core = match self.core {
+            Some(ref core) => core,
+            None => return,
+        };
+
+        let callback = if installed {
+            Some(crate::frame_callback as unsafe extern "C" fn(c_uint))
+        } else {
+            None
+        };
+        match core.set_frame_callback(callback) {
+            Ok(()) => self.frame_callback = installed,
+            Err(e) => dprintln!("Unable to change the frame callback: {}", e),
+        }
+    }
+
+    /// Called by the core after every frame it renders.
+    ///
+    /// Greenzone states are taken, seeks are finished and breakpoints are checked here, between
+    /// the inputs of two frames.
+    pub fn frame_done(&self) {
+        let frame = self.frame();
+        self.update_greenzone(frame);
+        if self.shared.seek.lock().unwrap().is_some() {
+            self.finish_seek(frame);
+        } else {
+            self.check_breakpoints(frame);
+        }
+    }
+
+    // pause before a frame if it hits a breakpoint
+    fn check_breakpoints(&self, frame: u64) {
+        let playing = *self.shared.movie_state.lock().unwrap() == MovieState::Playing;
+        let movie = self.shared.movie.lock().unwrap();
+        let hit = self
+            .shared
+            .breakpoints
+            .lock()
+            .unwrap()
+            .check(frame, &movie, playing);
+        drop(movie);
+
+        if let (Some(breakpoint), Some(core)) = (hit, self.core.as_ref()) {
+            dprintln!("Frame {}: hit breakpoint \"{}\"", frame, breakpoint);
+            if let Err(e) = core.set_paused(true) {
+                dprintln!("Unable to pause: {}", e);
+            }
+        }
     }
+
+    // take a greenzone state before a frame of the movie, if one is due
+    fn update_greenzone(&self, frame: u64) {
+        if *self.shared.movie_state.lock().unwrap() == MovieState::Stopped {
+            return;
+        }
+        let mut greenzone = self.shared.greenzone.lock().unwrap();
+        let (core, path) = match (self.core.as_ref(), greenzone.path(frame)) {
+            (Some(core), Some(path)) if greenzone.wants(frame) => (core, path),
+            _ => return,
+        };
+        match core.save_state(&path) {
+            Ok(()) => greenzone.insert(frame),
+            Err(e) => dprintln!("Unable to take greenzone state for frame {}: {}", frame, e),
+        }
+    }
+
+    // pause before the frame being seeked to, at the normal speed again
+    fn finish_seek(&self, frame: u64) {
+        let mut seek = self.shared.seek.lock().unwrap();
+        let (resume, speed_limiter) = match *seek {
+            Some(s) if frame >= s.target => (s.resume, s.speed_limiter),
+            _ => return,
+        };
+        *seek = None;
+        *self.shared.movie_state.lock().unwrap() = resume;
+
+        dprintln!("Frame {}: reached the seek target", frame);
+        if let Some(ref core) = self.core {
+            let result = core
+                .set_paused(true)
+                .and_then(|_| core.set_speed_limiter(speed_limiter));
+            if let Err(e) = result {
+                dprintln!("Unable to pause: {}", e);
+            }
+        }
+    }
+
+    /// Drive the stick of a specific controller with a pattern, or stop driving it.
+    pub fn set_pattern(&self, control: usize, pattern: Option<StickPattern>) {
+        self.shared.edit_pattern(control, None, |generator| {
+            *generator = pattern.map(PatternGenerator::new)
+        });
+    }
+
+    /// Fill the upcoming frames of a controller with a stick ramp.
+    ///
+    /// Keyframes are counted from the next frame sent to the emulator. Frames that are not
+    /// queued yet are filled in with the inputs currently set in the GUI.
+    pub fn queue_ramp(&self, control: usize, ramp: &Ramp) {
+        let base = self.get_inputs(control);
+        self.shared
+            .edit_queue(control, None, |queue| queue_ramp(queue, base, ramp));
+    }
+
+    /// Drop any queued frames for a controller.
+    pub fn clear_queue(&self, control: usize) {
+        self.shared.edit_queue(control, None, VecDeque::clear);
+    }
+
+    /// The number of frames queued for a controller.
+    pub fn queued_frames(&self, control: usize) -> usize {
+        self.shared.queues[control].lock().unwrap().len()
+    }
+
+    /// The current clamp mode for the stick.
+    pub fn clamp(&self) -> ClampMode {
+        *self.shared.clamp.lock().unwrap()
+    }
+
+    /// Set the current clamp mode for the stick.
+    pub fn set_clamp(&self, clamp: ClampMode) {
+        *self.shared.clamp.lock().unwrap() = clamp;
+    }
+}
+
+/// Fill a queue of upcoming frames with a stick ramp, padding it with `base` as needed.
+pub fn queue_ramp(queue: &mut VecDeque<Inputs>, base: Inputs, ramp: &Ramp) {
+    let start: usize = ramp.start().frame.try_into().unwrap_or(usize::MAX);
+    let needed = start.saturating_add(ramp.frame_count());
+    while queue.len() < needed {
+        queue.push_back(base);
+    }
+
+    ramp.apply(queue.iter_mut().skip(start));
 }
 
 impl Default for Tasinput2State {
@@ -18,29 +18,226 @@
  * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use crate::{Controller, Inputs, CONTROLLER_COUNT};
-use qt_widgets::QApplication;
-use std::sync::{Arc, Mutex};
+use super::SharedState;
+use crate::{
+    config::{ConfigureFunctions, Settings, WindowGeometry, WindowMode},
+    core_lib::Core,
+    piano_roll::{PianoRoll, NEXT_MARKER_KEY, PREVIOUS_MARKER_KEY},
+    Controller, CONTROLLER_COUNT,
+};
+use qt_widgets::{
+    cpp_core::MutPtr,
+    qt_core::{QString, QTimer, Slot, SlotOfInt, WindowType},
+    qt_gui::QKeySequence,
+    QApplication, QComboBox, QShortcut, QStackedWidget, QTabWidget, QVBoxLayout, QWidget,
+};
+use std::cell::Cell;
+
+// the label used for a port in tabs and dropdowns
+fn port_label(port: usize) -> String {
+    format!("Port {}", port + 1)
+}
+
+// move a window to where it was last time
+unsafe fn restore_geometry(mut window: MutPtr<QWidget>, geometry: &WindowGeometry) {
+    if geometry.is_set() {
+        window.set_geometry_4a(geometry.x, geometry.y, geometry.width, geometry.height);
+    }
+    window.set_window_flag_2a(WindowType::WindowStaysOnTopHint, geometry.always_on_top);
+}
+
+// get the current position of a window
+unsafe fn read_geometry(window: MutPtr<QWidget>) -> WindowGeometry {
+    let rect = window.geometry();
+    WindowGeometry {
+        x: rect.x(),
+        y: rect.y(),
+        width: rect.width(),
+        height: rect.height(),
+        always_on_top: window
+            .window_flags()
+            .test_flag(WindowType::WindowStaysOnTopHint),
+    }
+}
 
 pub unsafe fn qt_thread(
-    controllers: [bool; CONTROLLER_COUNT],
-    inputs: Arc<[Arc<Mutex<Inputs>>; CONTROLLER_COUNT]>,
+    settings: Settings,
+    config: Option<ConfigureFunctions>,
+    core: Option<Core>,
+    shared: SharedState,
 ) {
     QApplication::init(move |_| {
+        let mut settings = settings;
         let mut controller_windows = Vec::new();
 
-        // start up the controller windows
-        //let mut controller_windows = [None; CONTROLLER_COUNT];
-        for (i, do_init) in controllers.iter().enumerate() {
-            if !do_init {
-                continue;
+        // start up the controller windows, for the ports that start out unplugged as well so
+        // they can be plugged in later
+        for i in 0..CONTROLLER_COUNT {
+            dprintln!("Creating controller #{}", i);
+
+            controller_windows.push((i, Controller::new(i, settings.devices[i], &shared)));
+        }
+        let plugged = *shared.plugged.lock().unwrap();
+
+        // arrange the windows
+        let mut container = QWidget::new_0a();
+        container.set_window_title(&QString::from_std_str("TAS Input"));
+        let mut layout = QVBoxLayout::new_1a(&mut container).into_ptr();
+        let mut port_select = QComboBox::new_0a();
+        let mut stack = QStackedWidget::new_0a();
+        let mut tabs = QTabWidget::new_0a();
+
+        match settings.window_mode {
+            WindowMode::Separate => {
+                for (i, controller) in controller_windows.iter_mut() {
+                    restore_geometry(controller.window(), &settings.windows[*i]);
+                    controller.window().set_visible(plugged[*i]);
+                }
+            }
+            WindowMode::Tabbed => {
+                for (i, controller) in controller_windows.iter_mut() {
+                    tabs.add_tab_2a(controller.window(), &QString::from_std_str(port_label(*i)));
+                }
+                layout.add_widget(&mut tabs);
+                restore_geometry(container.as_mut_ptr(), &settings.shared_window);
+                container.show();
+            }
+            WindowMode::Switchable => {
+                for (i, controller) in controller_windows.iter_mut() {
+                    port_select.add_item_q_string(&QString::from_std_str(port_label(*i)));
+                    stack.add_widget(controller.window());
+                }
+                layout.add_widget(&mut port_select);
+                layout.add_widget(&mut stack);
+                restore_geometry(container.as_mut_ptr(), &settings.shared_window);
+                container.show();
             }
+        }
 
-            dprintln!("Creating controller #{}", i);
+        // switch the port being edited when a different one is selected
+        let mut stack_ptr: MutPtr<QStackedWidget> = stack.as_mut_ptr();
+        let port_changed = SlotOfInt::new(move |index| stack_ptr.set_current_index(index));
+        port_select.current_index_changed().connect(&port_changed);
+
+        for (_, controller) in controller_windows.iter_mut() {
+            controller.refresh_always_on_top();
+        }
 
-            controller_windows.push(Controller::new(&inputs[i]));
+        // separate windows come up when their port is plugged in, from the GUI or by a movie,
+        // and go away when it is pulled out
+        let windows: Vec<MutPtr<QWidget>> = controller_windows
+            .iter_mut()
+            .map(|(_, controller)| controller.window())
+            .collect();
+        let plugged_reference = &shared.plugged;
+        let seen_plugged = Cell::new(plugged);
+        let plugged_windows = windows.clone();
+        let plugged_update = Slot::new(move || {
+            let plugged = *plugged_reference.lock().unwrap();
+            let seen = seen_plugged.replace(plugged);
+            for (port, window) in plugged_windows.iter().enumerate() {
+                if plugged[port] != seen[port] {
+                    let mut window = *window;
+                    window.set_visible(plugged[port]);
+                }
+            }
+        });
+        let mut plugged_timer = QTimer::new_0a();
+        plugged_timer.timeout().connect(&plugged_update);
+        if settings.window_mode == WindowMode::Separate {
+            plugged_timer.start_1a(50);
+        }
+
+        // alt and a port number bring up that port, to plug in one whose window is hidden
+        let window_mode = settings.window_mode;
+        let tabs_ptr: MutPtr<QTabWidget> = tabs.as_mut_ptr();
+        let port_select_ptr: MutPtr<QComboBox> = port_select.as_mut_ptr();
+        let show_ports: Vec<Slot> = windows
+            .iter()
+            .enumerate()
+            .map(|(port, window)| {
+                let (mut window, mut tabs, mut port_select) = (*window, tabs_ptr, port_select_ptr);
+                Slot::new(move || match window_mode {
+                    WindowMode::Separate => {
+                        window.show();
+                        window.raise();
+                    }
+                    WindowMode::Tabbed => tabs.set_current_index(port as i32),
+                    WindowMode::Switchable => port_select.set_current_index(port as i32),
+                })
+            })
+            .collect();
+
+        // the piano roll stays open between sessions, and ctrl + p brings it up from any port,
+        // where the marker hotkeys work as well
+        let mut piano_roll = PianoRoll::new(&shared, core.as_ref());
+        let mut piano_roll_window = piano_roll.window();
+        restore_geometry(piano_roll_window, &settings.piano_roll_window);
+        if settings.piano_roll {
+            piano_roll_window.show();
+        }
+        let show_piano_roll = Slot::new(move || {
+            piano_roll_window.show();
+            piano_roll_window.raise();
+        });
+        let mut hotkeys = vec![
+            ("Ctrl+P".to_string(), &show_piano_roll),
+            (NEXT_MARKER_KEY.to_string(), piano_roll.next_marker()),
+            (
+                PREVIOUS_MARKER_KEY.to_string(),
+                piano_roll.previous_marker(),
+            ),
+        ];
+        for (port, slot) in show_ports.iter().enumerate() {
+            hotkeys.push((format!("Alt+{}", port + 1), slot));
+        }
+        for (_, controller) in controller_windows.iter_mut() {
+            for (sequence, slot) in hotkeys.iter() {
+                let shortcut = QShortcut::new_2a(
+                    &QKeySequence::from_q_string(&QString::from_std_str(sequence)),
+                    controller.window(),
+                );
+                shortcut.activated().connect(*slot);
+                shortcut.into_ptr();
+            }
+        }
+
+        // the ports can be brought up from the piano roll too, when every window is hidden
+        for (port, slot) in show_ports.iter().enumerate() {
+            let shortcut = QShortcut::new_2a(
+                &QKeySequence::from_q_string(&QString::from_std_str(format!("Alt+{}", port + 1))),
+                piano_roll_window,
+            );
+            shortcut.activated().connect(slot);
+            shortcut.into_ptr();
+        }
+
+        let exit_code = QApplication::exec();
+
+        // remember where the windows were for next time
+        match settings.window_mode {
+            WindowMode::Separate => {
+                for (i, controller) in controller_windows.iter_mut() {
+                    if controller.window().is_visible() {
+                        settings.windows[*i] = read_geometry(controller.window());
+                    }
+                }
+            }
+            _ => settings.shared_window = read_geometry(container.as_mut_ptr()),
+        }
+        settings.piano_roll = piano_roll_window.is_visible();
+        if settings.piano_roll {
+            settings.piano_roll_window = read_geometry(piano_roll_window);
+        }
+        settings.controllers = *shared.plugged.lock().unwrap();
+        settings.paks = *shared.paks.lock().unwrap();
+        settings.record_mode = *shared.record_mode.lock().unwrap();
+        if let Some(ref config) = config {
+            if let Err(e) = settings.save(config) {
+                dprintln!("Unable to save settings: {}", e);
+            }
         }
 
-        QApplication::exec()
+        exit_code
     });
 }
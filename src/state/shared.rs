@@ -0,0 +1,479 @@
+/*
+ * src/state/shared.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::StateError;
+use crate::{
+    breakpoints::Breakpoints,
+    core_lib::Core,
+    history::{Edit, History},
+    movie::{
+        empty_frame, Branches, Frame, Greenzone, Movie, MovieState, RecordMode, Seek, TimedEvent,
+    },
+    pak::Pak,
+    ClampMode, InputSlots, Inputs, PatternGenerator, Rule, StickOrigin, CONTROLLER_COUNT,
+};
+use std::{
+    collections::VecDeque,
+    ops::Range,
+    sync::{Arc, Mutex},
+};
+
+/// Handles to the parts of the state that are shared between the emulator and the GUI.
+#[derive(Clone)]
+pub struct SharedState {
+    pub inputs: Arc<[Arc<Mutex<Inputs>>; CONTROLLER_COUNT]>,
+    pub patterns: Arc<[Arc<Mutex<Option<PatternGenerator>>>; CONTROLLER_COUNT]>,
+    pub queues: Arc<[Arc<Mutex<VecDeque<Inputs>>>; CONTROLLER_COUNT]>,
+    pub clamp: Arc<Mutex<ClampMode>>,
+    pub slots: Arc<Mutex<InputSlots>>,
+    /// Whether a controller is plugged into each port.
+    pub plugged: Arc<Mutex<[bool; CONTROLLER_COUNT]>>,
+    /// The pak chosen for each port.
+    pub paks: Arc<Mutex<[Pak; CONTROLLER_COUNT]>>,
+    /// Whether the motor of the rumble pak in each port is running.
+    pub rumble: Arc<[Arc<Mutex<bool>>; CONTROLLER_COUNT]>,
+    /// The stick origin of each controller, moved by holding L, R and Start.
+    pub origins: Arc<Mutex<[StickOrigin; CONTROLLER_COUNT]>>,
+    /// The number of frames of input sent to the emulator so far.
+    pub frame: Arc<Mutex<u64>>,
+    /// The rules broken by the last frame sent from each port.
+    pub broken_rules: Arc<Mutex<[Vec<Rule>; CONTROLLER_COUNT]>>,
+    /// The movie being edited.
+    pub movie: Arc<Mutex<Movie>>,
+    /// The frames of the movie changed since the piano roll last showed them.
+    pub changed_frames: Arc<Mutex<Option<Range<usize>>>>,
+    /// Whether the ports in the movie header are waiting to be plugged in.
+    pub header_pending: Arc<Mutex<bool>>,
+    /// Whether the movie is being played back or recorded over.
+    pub movie_state: Arc<Mutex<MovieState>>,
+    /// How the movie is recorded over.
+    pub record_mode: Arc<Mutex<RecordMode>>,
+    /// Whether each port is recorded while recording; the others play back from the movie.
+    pub recorded_ports: Arc<Mutex<[bool; CONTROLLER_COUNT]>>,
+    /// The movie being played to a frame, if any.
+    pub seek: Arc<Mutex<Option<Seek>>>,
+    /// The breakpoints that pause the emulator.
+    pub breakpoints: Arc<Mutex<Breakpoints>>,
+    /// The states taken while the movie plays, for seeking back.
+    pub greenzone: Arc<Mutex<Greenzone>>,
+    /// The branches saved for the current ROM.
+    pub branches: Arc<Mutex<Branches>>,
+    /// The edits made to the inputs and the movie, for undo and redo.
+    pub history: Arc<Mutex<History>>,
+}
+
+impl SharedState {
+    /// Create a new shared state.
+    pub fn new() -> SharedState {
+        SharedState {
+            inputs: Arc::new(array_init::array_init(|_| {
+                Arc::new(Mutex::new(Inputs::from_value(0)))
+            })),
+            patterns: Arc::new(array_init::array_init(|_| Arc::new(Mutex::new(None)))),
+            queues: Arc::new(array_init::array_init(|_| {
+                Arc::new(Mutex::new(VecDeque::new()))
+            })),
+            clamp: Arc::new(Mutex::new(ClampMode::default())),
+            slots: Arc::new(Mutex::new(InputSlots::new())),
+            plugged: Arc::new(Mutex::new([false; CONTROLLER_COUNT])),
+            paks: Arc::new(Mutex::new([Pak::None; CONTROLLER_COUNT])),
+            rumble: Arc::new(array_init::array_init(|_| Arc::new(Mutex::new(false)))),
+            origins: Arc::new(Mutex::new(Default::default())),
+            frame: Arc::new(Mutex::new(0)),
+            broken_rules: Arc::new(Mutex::new(Default::default())),
+            movie: Arc::new(Mutex::new(Movie::default())),
+            changed_frames: Arc::new(Mutex::new(None)),
+            header_pending: Arc::new(Mutex::new(false)),
+            movie_state: Arc::new(Mutex::new(MovieState::default())),
+            record_mode: Arc::new(Mutex::new(RecordMode::default())),
+            recorded_ports: Arc::new(Mutex::new([true; CONTROLLER_COUNT])),
+            seek: Arc::new(Mutex::new(None)),
+            breakpoints: Arc::new(Mutex::new(Breakpoints::new())),
+            greenzone: Arc::new(Mutex::new(Greenzone::new())),
+            branches: Arc::new(Mutex::new(Branches::new())),
+            history: Arc::new(Mutex::new(History::new())),
+        }
+    }
+
+    /// Change the inputs set in the GUI for a port, recording the change in the history.
+    pub fn edit_inputs<F: FnOnce(&mut Inputs)>(&self, port: usize, group: Option<u64>, edit: F) {
+        let mut inputs = self.inputs[port].lock().unwrap();
+        let before = *inputs;
+        edit(&mut inputs);
+        let after = *inputs;
+        drop(inputs);
+
+        self.history.lock().unwrap().record(
+            Edit::Inputs {
+                port,
+                before,
+                after,
+            },
+            group,
+        );
+    }
+
+    /// Change the stick pattern driving a port, recording the change in the history.
+    pub fn edit_pattern<F: FnOnce(&mut Option<PatternGenerator>)>(
+        &self,
+        port: usize,
+        group: Option<u64>,
+        edit: F,
+    ) {
+        let mut pattern = self.patterns[port].lock().unwrap();
+        let before = pattern.clone();
+        edit(&mut pattern);
+        let after = pattern.clone();
+        drop(pattern);
+
+        self.history.lock().unwrap().record(
+            Edit::Pattern {
+                port,
+                before,
+                after,
+            },
+            group,
+        );
+    }
+
+    /// Change the frames queued for a port, recording the change in the history.
+    pub fn edit_queue<F: FnOnce(&mut VecDeque<Inputs>)>(
+        &self,
+        port: usize,
+        group: Option<u64>,
+        edit: F,
+    ) {
+        let mut queue = self.queues[port].lock().unwrap();
+        let before = queue.clone();
+        edit(&mut queue);
+        let after = queue.clone();
+        drop(queue);
+
+        self.history.lock().unwrap().record(
+            Edit::Queue {
+                port,
+                before,
+                after,
+            },
+            group,
+        );
+    }
+
+    /// Change the movie, recording the frames, events and markers that changed in the history.
+    ///
+    /// The header is not part of the history.
+    pub fn edit_movie<F: FnOnce(&mut Movie)>(&self, group: Option<u64>, edit: F) {
+        let mut movie = self.movie.lock().unwrap();
+        let frames = movie.frames().to_vec();
+        let events = movie.events.clone();
+        let markers = movie.markers.clone();
+        edit(&mut movie);
+        let frames = Edit::frames(&frames, movie.frames());
+        let events = Edit::Events {
+            before: events,
+            after: movie.events.clone(),
+        };
+        let markers = Edit::Markers {
+            before: markers,
+            after: movie.markers.clone(),
+        };
+        drop(movie);
+
+        self.invalidate(&frames);
+        self.mark_changed(&frames);
+        self.mark_changed(&markers);
+
+        // the frames, events and markers are undone together
+        let mut history = self.history.lock().unwrap();
+        let group = group.unwrap_or_else(|| history.group());
+        history.record(frames, Some(group));
+        history.record(events, Some(group));
+        history.record(markers, Some(group));
+    }
+
+    /// Change a single frame of the movie, recording the change in the history.
+    ///
+    /// Unlike `edit_movie`, only the frame itself is compared, and the events and markers only
+    /// when an inserted frame moves some of them, so this is cheap enough to call on every frame
+    /// sent to the emulator. With `insert` the frame is edited as an empty frame inserted before
+    /// `index`. Frames past the end of the movie are filled in as empty.
+    pub fn edit_frame<F: FnOnce(&mut Frame)>(
+        &self,
+        group: u64,
+        index: usize,
+        insert: bool,
+        edit: F,
+    ) {
+        let mut movie = self.movie.lock().unwrap();
+        let start = index.min(movie.len());
+        // only an inserted frame moves the events and markers, and only those after it
+        let moves = |frame: Option<u64>| insert && matches!(frame, Some(f) if f >= start as u64);
+        let sidecars = if moves(movie.events.events().last().map(|e| e.frame))
+            || moves(movie.markers.list().last().map(|m| m.frame))
+        {
+            Some((movie.events.clone(), movie.markers.clone()))
+        } else {
+            None
+        };
+        let before: Vec<Frame> = match movie.frame(index) {
+            Some(frame) if !insert => vec![*frame],
+            _ => Vec::new(),
+        };
+
+        let mut frame = before.first().copied().unwrap_or_else(empty_frame);
+        edit(&mut frame);
+        let mut after = vec![empty_frame(); index - start];
+        after.push(frame);
+        if insert {
+            movie.insert_frames(start, after.clone());
+        } else {
+            movie.replace(start, before.len(), after.clone());
+        }
+        let sidecars = sidecars.map(|(events, markers)| {
+            let events = Edit::Events {
+                before: events,
+                after: movie.events.clone(),
+            };
+            let markers = Edit::Markers {
+                before: markers,
+                after: movie.markers.clone(),
+            };
+            (events, markers)
+        });
+        drop(movie);
+
+        let frames = Edit::Frames {
+            start,
+            before,
+            after,
+        };
+        // the states after the frame were taken with its old inputs, or before it was inserted
+        if !frames.is_empty() {
+            self.greenzone.lock().unwrap().invalidate(index as u64);
+        }
+        self.mark_changed(&frames);
+
+        let mut history = self.history.lock().unwrap();
+        history.record(frames, Some(group));
+        if let Some((events, markers)) = sidecars {
+            self.mark_changed(&markers);
+            history.record(events, Some(group));
+            history.record(markers, Some(group));
+        }
+    }
+
+    /// Add an event to the movie, recording the change in the history.
+    pub fn add_event(&self, group: u64, event: TimedEvent) {
+        let mut movie = self.movie.lock().unwrap();
+        let before = movie.events.clone();
+        movie.events.push(event);
+        let after = movie.events.clone();
+        drop(movie);
+
+        self.history
+            .lock()
+            .unwrap()
+            .record(Edit::Events { before, after }, Some(group));
+    }
+
+    /// Undo the last step in the history, returning whether there was one.
+    pub fn undo(&self) -> bool {
+        let edits = self.history.lock().unwrap().undo();
+        edits
+            .map(|edits| edits.iter().for_each(|e| self.apply(e)))
+            .is_some()
+    }
+
+    /// Redo the last undone step in the history, returning whether there was one.
+    pub fn redo(&self) -> bool {
+        let edits = self.history.lock().unwrap().redo();
+        edits
+            .map(|edits| edits.iter().for_each(|e| self.apply(e)))
+            .is_some()
+    }
+
+    /// Play the movie to a frame as fast as possible and pause there.
+    ///
+    /// Unless the frame is ahead of the current one with no greenzone state in between, the
+    /// nearest greenzone state before it is loaded first. Whatever was being done with the movie
+    /// carries on from the frame once the emulator is resumed. The seek is finished by
+    /// `Tasinput2State::frame_done`.
+    pub fn seek(&self, core: &Core, target: u64) -> Result<(), StateError> {
+        let current = *self.frame.lock().unwrap();
+        let target = target.min(self.movie.lock().unwrap().len() as u64);
+
+        let greenzone = self.greenzone.lock().unwrap();
+        let start = match greenzone.nearest(target) {
+            Some(nearest) if target < current || nearest > current => nearest,
+            _ if target >= current => current,
+            _ => {
+                return Err(StateError::StaticMsg(
+                    "There is no greenzone state before that frame",
+                ))
+            }
+        };
+        if start != current {
+            core.load_state(&greenzone.path(start).unwrap())?;
+            *self.frame.lock().unwrap() = start;
+        }
+        drop(greenzone);
+
+        // seeking again keeps what was being done before the first seek
+        let mut seek = self.seek.lock().unwrap();
+        let mut movie_state = self.movie_state.lock().unwrap();
+        let resume = seek.map_or(*movie_state, |s| s.resume);
+        let speed_limiter = match *seek {
+            Some(s) => s.speed_limiter,
+            None => core.speed_limiter()?,
+        };
+        if start == target {
+            *seek = None;
+            *movie_state = resume;
+            core.set_speed_limiter(speed_limiter)?;
+            return core.set_paused(true);
+        }
+        *seek = Some(Seek {
+            target,
+            resume,
+            speed_limiter,
+        });
+        *movie_state = MovieState::Playing;
+        drop(seek);
+        drop(movie_state);
+        core.set_speed_limiter(false)?;
+        core.set_paused(false)
+    }
+
+    /// The revision of the history, which changes with every edit, undo and redo.
+    pub fn revision(&self) -> u64 {
+        self.history.lock().unwrap().revision()
+    }
+
+    // put an edit into effect without recording it
+    fn apply(&self, edit: &Edit) {
+        match edit {
+            Edit::Inputs { port, after, .. } => *self.inputs[*port].lock().unwrap() = *after,
+            Edit::Pattern { port, after, .. } => {
+                *self.patterns[*port].lock().unwrap() = after.clone()
+            }
+            Edit::Queue { port, after, .. } => *self.queues[*port].lock().unwrap() = after.clone(),
+            Edit::Frames {
+                start,
+                before,
+                after,
+            } => {
+                self.movie
+                    .lock()
+                    .unwrap()
+                    .replace(*start, before.len(), after.clone());
+                self.invalidate(edit);
+                self.mark_changed(edit);
+            }
+            Edit::Events { after, .. } => self.movie.lock().unwrap().events = after.clone(),
+            Edit::Markers { after, .. } => {
+                self.movie.lock().unwrap().markers = after.clone();
+                self.mark_changed(edit);
+            }
+        }
+    }
+
+    // note the frames an edit changed, for the piano roll to show them again
+    fn mark_changed(&self, edit: &Edit) {
+        let range = match edit {
+            _ if edit.is_empty() => return,
+            Edit::Frames {
+                start,
+                before,
+                after,
+            } if before.len() == after.len() => *start..start + after.len(),
+            // everything after frames that were inserted or removed moves
+            Edit::Frames { start, .. } => *start..usize::MAX,
+            Edit::Markers { before, after } => {
+                let (before, after) = (before.list(), after.list());
+                let frames = before
+                    .iter()
+                    .filter(|m| !after.contains(m))
+                    .chain(after.iter().filter(|m| !before.contains(m)))
+                    .map(|m| m.frame as usize);
+                match (frames.clone().min(), frames.max()) {
+                    (Some(first), Some(last)) => first..last + 1,
+                    _ => return,
+                }
+            }
+            _ => return,
+        };
+
+        let mut changed = self.changed_frames.lock().unwrap();
+        *changed = Some(match changed.take() {
+            Some(c) => c.start.min(range.start)..c.end.max(range.end),
+            None => range,
+        });
+    }
+
+    // drop the greenzone states made stale by a change to the frames
+    fn invalidate(&self, edit: &Edit) {
+        if let Edit::Frames { start, .. } = edit {
+            if !edit.is_empty() {
+                self.greenzone.lock().unwrap().invalidate(*start as u64);
+            }
+        }
+    }
+}
+
+impl Default for SharedState {
+    fn default() -> SharedState {
+        Self::new()
+    }
+}
+
+#[test]
+fn edit_frame_test() {
+    let path = std::env::temp_dir().join(format!("tasinput2-greenzone-{}", std::process::id()));
+    let shared = SharedState::new();
+    *shared.greenzone.lock().unwrap() = Greenzone::open(&path, 10, 10);
+    shared.edit_movie(None, |movie| {
+        movie.insert_frames(0, vec![empty_frame(); 40])
+    });
+    for frame in [10, 20, 30].iter() {
+        shared.greenzone.lock().unwrap().insert(*frame);
+    }
+    let states = || {
+        shared
+            .greenzone
+            .lock()
+            .unwrap()
+            .frames()
+            .collect::<Vec<_>>()
+    };
+
+    // the state before the edited frame still holds, the ones after it don't
+    shared.edit_frame(0, 20, false, |frame| frame[0] = Inputs::from_value(1));
+    assert_eq!(states(), vec![10, 20]);
+
+    // an unchanged frame leaves them alone
+    shared.edit_frame(0, 5, false, |_| ());
+    assert_eq!(states(), vec![10, 20]);
+
+    // an inserted frame moves every frame after it
+    shared.edit_frame(0, 10, true, |_| ());
+    assert_eq!(states(), vec![10]);
+
+    let _ = std::fs::remove_dir_all(path);
+}
@@ -0,0 +1,100 @@
+/*
+ * src/stick/clamp.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::to_axis;
+
+/// How far a real controller's stick reaches along the axes.
+pub const GATE_CARDINAL: f64 = 85.0;
+/// How far a real controller's stick reaches along each axis on the diagonals.
+pub const GATE_DIAGONAL: f64 = 70.0;
+
+/// How the range of the analog stick is restricted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClampMode {
+    /// Pass coordinates through untouched.
+    Raw,
+    /// Keep the stick within a circle with a radius of 127.
+    Circle,
+    /// Keep the stick within the octagonal gate of a real controller.
+    Gate,
+}
+
+impl Default for ClampMode {
+    fn default() -> ClampMode {
+        ClampMode::Raw
+    }
+}
+
+// the scale to apply to (x, y) to put it on the segment between a and b, if it is outside
+fn scale_to_edge(x: f64, y: f64, a: (f64, f64), b: (f64, f64)) -> f64 {
+    let edge = (b.0 - a.0, b.1 - a.1);
+    let cross = |u: (f64, f64), v: (f64, f64)| (u.0 * v.1) - (u.1 * v.0);
+    cross(a, edge) / cross((x, y), edge)
+}
+
+impl ClampMode {
+    /// Restrict a stick position to the range allowed by this mode.
+    pub fn clamp(self, x: i8, y: i8) -> (i8, i8) {
+        let (fx, fy) = (f64::from(x), f64::from(y));
+
+        let scale = match self {
+            ClampMode::Raw => 1.0,
+            ClampMode::Circle => 127.0 / fx.hypot(fy),
+            ClampMode::Gate => {
+                // fold the position into the first octant pair to find the edge it points at
+                let (ax, ay) = (fx.abs(), fy.abs());
+                let corner = (GATE_DIAGONAL, GATE_DIAGONAL);
+                if ay <= ax {
+                    scale_to_edge(ax, ay, (GATE_CARDINAL, 0.0), corner)
+                } else {
+                    scale_to_edge(ax, ay, (0.0, GATE_CARDINAL), corner)
+                }
+            }
+        };
+
+        if scale.is_finite() && scale < 1.0 {
+            (to_axis(fx * scale), to_axis(fy * scale))
+        } else {
+            (x, y)
+        }
+    }
+
+    /// Tell whether a stick position is within the range allowed by this mode.
+    pub fn contains(self, x: i8, y: i8) -> bool {
+        self.clamp(x, y) == (x, y)
+    }
+}
+
+#[test]
+fn circle_clamp_test() {
+    assert_eq!(ClampMode::Circle.clamp(127, 0), (127, 0));
+    assert_eq!(ClampMode::Circle.clamp(-128, 0), (-127, 0));
+    assert_eq!(ClampMode::Circle.clamp(127, 127), (90, 90));
+    assert_eq!(ClampMode::Raw.clamp(127, 127), (127, 127));
+}
+
+#[test]
+fn gate_clamp_test() {
+    assert_eq!(ClampMode::Gate.clamp(127, 0), (85, 0));
+    assert_eq!(ClampMode::Gate.clamp(0, -127), (0, -85));
+    assert_eq!(ClampMode::Gate.clamp(127, 127), (70, 70));
+    assert_eq!(ClampMode::Gate.clamp(40, -40), (40, -40));
+    assert!(!ClampMode::Gate.contains(80, 80));
+}
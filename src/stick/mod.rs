@@ -0,0 +1,45 @@
+/*
+ * src/stick/mod.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Utilities for driving the analog stick programmatically.
+
+mod clamp;
+mod origin;
+mod pattern;
+mod ramp;
+
+pub use clamp::{ClampMode, GATE_CARDINAL, GATE_DIAGONAL};
+pub use origin::StickOrigin;
+pub use pattern::{PatternGenerator, StickPattern};
+pub use ramp::{Interpolation, Keyframe, Ramp};
+
+use std::convert::TryInto;
+
+// convert a floating point stick coordinate to the nearest valid stick value
+fn to_axis(value: f64) -> i8 {
+    let value = value.round();
+    if value >= 127.0 {
+        127
+    } else if value <= -128.0 {
+        -128
+    } else {
+        (value as i32).try_into().unwrap_or(0)
+    }
+}
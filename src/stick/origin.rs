@@ -0,0 +1,83 @@
+/*
+ * src/stick/origin.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::to_axis;
+use crate::Inputs;
+
+/// The position a controller treats as the center of its stick.
+///
+/// Holding L, R and Start makes a real controller take the current stick position as its new
+/// center, so every later position is reported relative to it.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct StickOrigin {
+    pub x: i8,
+    pub y: i8,
+}
+
+impl StickOrigin {
+    /// Tell whether a set of inputs holds the recalibration combo.
+    pub fn is_combo(inputs: &Inputs) -> bool {
+        inputs.l && inputs.r && inputs.start
+    }
+
+    /// Shift a set of inputs to be relative to this origin, recalibrating first if they hold
+    /// the combo.
+    ///
+    /// Returns whether the origin changed. As on hardware, Start is not reported while the
+    /// combo is held.
+    pub fn apply(&mut self, inputs: &mut Inputs) -> bool {
+        let mut changed = false;
+        if StickOrigin::is_combo(inputs) {
+            changed = (self.x, self.y) != (inputs.x, inputs.y);
+            self.x = inputs.x;
+            self.y = inputs.y;
+            inputs.start = false;
+        }
+
+        inputs.x = to_axis(inputs.x as f64 - self.x as f64);
+        inputs.y = to_axis(inputs.y as f64 - self.y as f64);
+        changed
+    }
+}
+
+#[test]
+fn recalibration_test() {
+    let mut origin = StickOrigin::default();
+
+    let mut inputs = Inputs::from_value(0);
+    inputs.x = 20;
+    inputs.y = -10;
+    assert!(!origin.apply(&mut inputs));
+    assert_eq!((inputs.x, inputs.y), (20, -10));
+
+    let mut combo = Inputs::from_value(0);
+    combo.l = true;
+    combo.r = true;
+    combo.start = true;
+    combo.x = 20;
+    combo.y = -10;
+    assert!(origin.apply(&mut combo));
+    assert_eq!((combo.x, combo.y, combo.start), (0, 0, false));
+
+    let mut inputs = Inputs::from_value(0);
+    inputs.x = -120;
+    assert!(!origin.apply(&mut inputs));
+    assert_eq!((inputs.x, inputs.y), (-128, 10));
+}
@@ -0,0 +1,153 @@
+/*
+ * src/stick/pattern.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::to_axis;
+use crate::Inputs;
+
+/// A repeating movement of the analog stick.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StickPattern {
+    /// Rotate around the center of the stick.
+    ///
+    /// `speed` is given in degrees per frame; negative speeds rotate clockwise.
+    Spin {
+        radius: f64,
+        speed: f64,
+        start_angle: f64,
+    },
+    /// Move back and forth between two points, taking `period` frames for a full cycle.
+    Oscillate {
+        from: (i8, i8),
+        to: (i8, i8),
+        period: u32,
+    },
+    /// Cycle through a list of positions, holding each one for `hold` frames.
+    Alternate { positions: Vec<(i8, i8)>, hold: u32 },
+}
+
+impl StickPattern {
+    /// Get the stick position at a certain number of frames since the pattern started.
+    pub fn position(&self, step: u64) -> (i8, i8) {
+        match self {
+            StickPattern::Spin {
+                radius,
+                speed,
+                start_angle,
+            } => {
+                let angle = (start_angle + speed * step as f64).to_radians();
+                (to_axis(radius * angle.cos()), to_axis(radius * angle.sin()))
+            }
+            StickPattern::Oscillate { from, to, period } => {
+                // a period of less than two frames would never reach the second point
+                let period = u64::from(*period).max(2);
+                let t = (step % period) as f64 / period as f64;
+                let t = if t < 0.5 { t * 2.0 } else { 2.0 - (t * 2.0) };
+
+                let lerp = |a: i8, b: i8| to_axis(f64::from(a) + (f64::from(b) - f64::from(a)) * t);
+                (lerp(from.0, to.0), lerp(from.1, to.1))
+            }
+            StickPattern::Alternate { positions, hold } => {
+                if positions.is_empty() {
+                    return (0, 0);
+                }
+
+                let hold = u64::from(*hold).max(1);
+                positions[((step / hold) % positions.len() as u64) as usize]
+            }
+        }
+    }
+}
+
+/// Produces the stick position for each successive frame of a pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternGenerator {
+    pattern: StickPattern,
+    step: u64,
+}
+
+impl PatternGenerator {
+    /// Start a new generator at the beginning of a pattern.
+    pub fn new(pattern: StickPattern) -> PatternGenerator {
+        PatternGenerator { pattern, step: 0 }
+    }
+
+    /// The pattern this generator follows.
+    pub fn pattern(&self) -> &StickPattern {
+        &self.pattern
+    }
+
+    /// Write the next position into a set of inputs.
+    pub fn apply(&mut self, inputs: &mut Inputs) {
+        if let Some((x, y)) = self.next() {
+            inputs.x = x;
+            inputs.y = y;
+        }
+    }
+}
+
+impl Iterator for PatternGenerator {
+    type Item = (i8, i8);
+
+    fn next(&mut self) -> Option<(i8, i8)> {
+        let position = self.pattern.position(self.step);
+        self.step = self.step.wrapping_add(1);
+        Some(position)
+    }
+}
+
+#[test]
+fn spin_test() {
+    let spin = StickPattern::Spin {
+        radius: 100.0,
+        speed: 90.0,
+        start_angle: 0.0,
+    };
+    assert_eq!(spin.position(0), (100, 0));
+    assert_eq!(spin.position(1), (0, 100));
+    assert_eq!(spin.position(2), (-100, 0));
+    assert_eq!(spin.position(3), (0, -100));
+}
+
+#[test]
+fn oscillate_test() {
+    let wiggle = StickPattern::Oscillate {
+        from: (-50, 0),
+        to: (50, 20),
+        period: 4,
+    };
+    assert_eq!(wiggle.position(0), (-50, 0));
+    assert_eq!(wiggle.position(1), (0, 10));
+    assert_eq!(wiggle.position(2), (50, 20));
+    assert_eq!(wiggle.position(3), (0, 10));
+    assert_eq!(wiggle.position(4), (-50, 0));
+}
+
+#[test]
+fn alternate_test() {
+    let mut gen = PatternGenerator::new(StickPattern::Alternate {
+        positions: vec![(1, 2), (3, 4), (5, 6)],
+        hold: 2,
+    });
+    let positions: Vec<(i8, i8)> = gen.by_ref().take(7).collect();
+    assert_eq!(
+        positions,
+        vec![(1, 2), (1, 2), (3, 4), (3, 4), (5, 6), (5, 6), (1, 2)]
+    );
+}
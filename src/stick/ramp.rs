@@ -0,0 +1,216 @@
+/*
+ * src/stick/ramp.rs
+ * tasinput2 - Plugin for creating TAS inputs
+ *
+ * This file is part of tasinput2.
+ *
+ * tasinput2 is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * tasinput2 is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with tasinput2.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use super::{to_axis, ClampMode};
+use crate::Inputs;
+use std::{convert::TryInto, f64::consts::PI};
+
+/// How the frames between two keyframes are filled in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Move in a straight line at a constant speed.
+    Linear,
+    /// Move in a straight line, speeding up and slowing down at the ends.
+    Eased,
+    /// Rotate along the shortest arc between the two angles.
+    Angular,
+}
+
+impl Interpolation {
+    /// Every interpolation, in the order they are presented to the user.
+    pub const ALL: [Interpolation; 3] = [
+        Interpolation::Linear,
+        Interpolation::Eased,
+        Interpolation::Angular,
+    ];
+
+    /// The name shown to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            Interpolation::Linear => "Linear",
+            Interpolation::Eased => "Eased",
+            Interpolation::Angular => "Shortest Arc",
+        }
+    }
+}
+
+/// A stick position at a certain frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Keyframe {
+    pub frame: u64,
+    pub x: i8,
+    pub y: i8,
+}
+
+impl Keyframe {
+    /// Instantiate a new keyframe.
+    pub fn new(frame: u64, x: i8, y: i8) -> Keyframe {
+        Keyframe { frame, x, y }
+    }
+}
+
+/// A transition of the stick between two keyframes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ramp {
+    start: Keyframe,
+    end: Keyframe,
+    interpolation: Interpolation,
+    clamp: ClampMode,
+}
+
+impl Ramp {
+    /// Create a new ramp. The keyframes may be given in either order.
+    pub fn new(
+        start: Keyframe,
+        end: Keyframe,
+        interpolation: Interpolation,
+        clamp: ClampMode,
+    ) -> Ramp {
+        let (start, end) = if start.frame <= end.frame {
+            (start, end)
+        } else {
+            (end, start)
+        };
+
+        Ramp {
+            start,
+            end,
+            interpolation,
+            clamp,
+        }
+    }
+
+    /// The first frame of the ramp.
+    pub fn start(&self) -> Keyframe {
+        self.start
+    }
+
+    /// The last frame of the ramp.
+    pub fn end(&self) -> Keyframe {
+        self.end
+    }
+
+    /// The number of frames the ramp covers, including both keyframes.
+    pub fn frame_count(&self) -> usize {
+        (self.end.frame - self.start.frame + 1)
+            .try_into()
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Get the stick position at a certain frame.
+    pub fn position(&self, frame: u64) -> (i8, i8) {
+        let frame = frame.max(self.start.frame).min(self.end.frame);
+        let t = if self.start.frame == self.end.frame {
+            1.0
+        } else {
+            (frame - self.start.frame) as f64 / (self.end.frame - self.start.frame) as f64
+        };
+
+        let (x0, y0) = (f64::from(self.start.x), f64::from(self.start.y));
+        let (x1, y1) = (f64::from(self.end.x), f64::from(self.end.y));
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+
+        let (x, y) = match self.interpolation {
+            Interpolation::Linear => (lerp(x0, x1, t), lerp(y0, y1, t)),
+            Interpolation::Eased => {
+                let t = t * t * (3.0 - 2.0 * t);
+                (lerp(x0, x1, t), lerp(y0, y1, t))
+            }
+            Interpolation::Angular => {
+                let (m0, m1) = (x0.hypot(y0), x1.hypot(y1));
+
+                // a centered keyframe has no angle of its own, so borrow the other one's
+                let a1 = if m1 == 0.0 {
+                    y0.atan2(x0)
+                } else {
+                    y1.atan2(x1)
+                };
+                let a0 = if m0 == 0.0 { a1 } else { y0.atan2(x0) };
+
+                let mut delta = a1 - a0;
+                while delta > PI {
+                    delta -= 2.0 * PI;
+                }
+                while delta < -PI {
+                    delta += 2.0 * PI;
+                }
+
+                let angle = a0 + delta * t;
+                let magnitude = lerp(m0, m1, t);
+                (magnitude * angle.cos(), magnitude * angle.sin())
+            }
+        };
+
+        self.clamp.clamp(to_axis(x), to_axis(y))
+    }
+
+    /// Write the stick positions into a series of frames, the first of which corresponds to the
+    /// starting keyframe.
+    pub fn apply<'a, I: IntoIterator<Item = &'a mut Inputs>>(&self, frames: I) {
+        for (frame, inputs) in (self.start.frame..=self.end.frame).zip(frames) {
+            let (x, y) = self.position(frame);
+            inputs.x = x;
+            inputs.y = y;
+        }
+    }
+}
+
+#[test]
+fn linear_ramp_test() {
+    let ramp = Ramp::new(
+        Keyframe::new(14, 100, -20),
+        Keyframe::new(10, 0, 20),
+        Interpolation::Linear,
+        ClampMode::Raw,
+    );
+    assert_eq!(ramp.frame_count(), 5);
+    assert_eq!(ramp.position(10), (0, 20));
+    assert_eq!(ramp.position(12), (50, 0));
+    assert_eq!(ramp.position(14), (100, -20));
+
+    let mut track = vec![Inputs::default(); 13];
+    ramp.apply(track[10..].iter_mut());
+    assert_eq!((track[9].x, track[9].y), (0, 0));
+    assert_eq!((track[11].x, track[11].y), (25, 10));
+    assert_eq!((track[12].x, track[12].y), (50, 0));
+}
+
+#[test]
+fn angular_ramp_test() {
+    // the shortest arc from just above -x to just below it crosses -x, not +x
+    let ramp = Ramp::new(
+        Keyframe::new(0, -100, 10),
+        Keyframe::new(2, -100, -10),
+        Interpolation::Angular,
+        ClampMode::Raw,
+    );
+    let (x, y) = ramp.position(1);
+    assert!(x < -99);
+    assert_eq!(y, 0);
+
+    let clamped = Ramp::new(
+        Keyframe::new(0, 127, 0),
+        Keyframe::new(4, 0, 127),
+        Interpolation::Angular,
+        ClampMode::Gate,
+    );
+    assert_eq!(clamped.position(0), (85, 0));
+    assert_eq!(clamped.position(2), (70, 70));
+}